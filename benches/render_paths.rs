@@ -0,0 +1,87 @@
+//! Compares the two ways of getting a frame onto an SDL canvas: a `fill_rect` per column straight onto the
+//! canvas, and drawing into a `Framebuffer` that's uploaded through a streaming texture.
+//!
+//! Runs on SDL's software renderer, so it needs no window. Run with `cargo bench --bench render_paths`
+
+use std::time::{Duration, Instant};
+
+use raycast::{
+    render::{
+        self,
+        canvas::{self, StreamingTexture},
+        Camera, Frame, Framebuffer, Renderer, Viewport,
+    },
+    Color, Map,
+};
+use sdl2::{pixels::PixelFormatEnum, render::BlendMode, surface::Surface};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+const FRAMES: u32 = 100;
+const MAPS: [&str; 4] = [
+    "assets/maps/standard.json",
+    "assets/maps/many_walls.json",
+    "assets/maps/courtyard.json",
+    "assets/maps/portals.json",
+];
+
+fn main() -> Result<(), String> {
+    let surface = Surface::new(WIDTH, HEIGHT, PixelFormatEnum::RGBA32)?;
+    let mut canvas = surface.into_canvas()?;
+    canvas.set_blend_mode(BlendMode::Blend);
+    let texture_creator = canvas.texture_creator();
+
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    let mut streaming = StreamingTexture::new(&texture_creator, WIDTH, HEIGHT)?;
+
+    println!("{} frames at {}x{}", FRAMES, WIDTH, HEIGHT);
+    for path in MAPS.iter() {
+        let map = Map::load(path).map_err(|e| e.to_string())?;
+        let sky = match &map.sky {
+            Some(sky) => Some(canvas::texture_from_image(&texture_creator, sky)?),
+            None => None,
+        };
+
+        let camera = Camera {
+            position: map.spawn.position,
+            angle: map.spawn.angle,
+            fov: 60.,
+        };
+        let mut frame = Frame::new(camera, Viewport::new(WIDTH, HEIGHT));
+        frame.cast(&map, camera, 0.);
+
+        let canvas_time = time(|| {
+            Renderer::clear(&mut canvas, Color::BLACK)?;
+            render::draw_frame(&mut canvas, &frame, &map, sky.as_ref())
+        })?;
+        let framebuffer_time = time(|| {
+            framebuffer.clear(Color::BLACK)?;
+            render::draw_frame(&mut framebuffer, &frame, &map, map.sky.as_ref())?;
+            streaming.upload(&framebuffer)?;
+            streaming.present(&mut canvas)
+        })?;
+
+        println!(
+            "{:<32} canvas {:>8.3}ms  framebuffer {:>8.3}ms",
+            path,
+            per_frame_ms(canvas_time),
+            per_frame_ms(framebuffer_time),
+        );
+    }
+
+    Ok(())
+}
+
+/// How long drawing `FRAMES` frames takes, after one to warm up
+fn time(mut draw: impl FnMut() -> Result<(), String>) -> Result<Duration, String> {
+    draw()?;
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        draw()?;
+    }
+    Ok(start.elapsed())
+}
+
+fn per_frame_ms(total: Duration) -> f64 {
+    total.as_secs_f64() * 1000. / FRAMES as f64
+}
@@ -1,19 +1,56 @@
+mod commands;
+mod menus;
+mod recording;
+mod replays;
+mod screenshot;
+
 use std::time::Instant;
 
 use factor::factor::factor;
 use lazy_static::lazy_static;
 use sdl2::{
-    event::Event,
-    keyboard::Keycode,
+    event::{Event, WindowEvent},
+    keyboard::{Keycode, Mod},
+    mouse::MouseUtil,
     pixels::Color,
-    rect::Rect,
-    render::Canvas,
-    ttf::{self, FontStyle},
-    video::Window,
+    render::{BlendMode, Canvas},
+    video::{FullscreenType, Window, WindowPos},
     EventPump,
 };
 
-use crate::{state::State, WINDOW_HEIGHT, WINDOW_WIDTH};
+use raycast::render::{
+    self,
+    canvas::{self, StreamingTexture},
+    DynamicResolution, Framebuffer, Renderer, Viewport,
+};
+
+use self::{
+    menus::GameState,
+    recording::{Recording, RecordingFormat, DEFAULT_RECORDING_FPS},
+    replays::ReplayMode,
+};
+use crate::{
+    console::Console,
+    debug_overlay::{DebugOverlay, FrameTimings},
+    key_state_handler::{Key, KeyStateHandler},
+    menu::Menu,
+    state::State,
+    WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+
+const MIN_RENDER_SCALE: f64 = 0.25;
+/// How much the render scale keys change it by
+const RENDER_SCALE_STEP: f64 = 0.05;
+/// Frame rate dynamic resolution aims for when turned on with a key
+const DYNAMIC_RESOLUTION_FPS: f64 = 60.;
+/// Degrees the field of view keys change it by
+const FOV_STEP: f64 = 5.;
+/// How much the minimap zoom keys magnify it by
+const MINIMAP_ZOOM_STEP: f64 = 1.25;
+/// Text on the window is drawn in this, rather than the bitmap font, when it loads
+const FONT_PATH: &str = "./assets/font/Pixeboy.ttf";
+/// Pixeboy's pixels line up with the screen's at multiples of this size
+const FONT_POINT_SIZE: u16 = 14;
 
 #[derive(PartialEq, Eq)]
 enum ControlFlow {
@@ -21,11 +58,80 @@ enum ControlFlow {
     Break,
 }
 
+/// How the view gets onto the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Straight onto the canvas, a `fill_rect` per column
+    Canvas,
+    /// Into a `Framebuffer` on the CPU, uploaded to a streaming texture once a frame
+    Framebuffer,
+}
+impl RenderPath {
+    fn toggled(self) -> Self {
+        match self {
+            Self::Canvas => Self::Framebuffer,
+            Self::Framebuffer => Self::Canvas,
+        }
+    }
+}
+
+/// How the window sits on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    /// A resizable window, `WINDOW_WIDTH` by `WINDOW_HEIGHT` to begin with
+    Windowed,
+    /// A window without borders covering the screen at the desktop's resolution
+    Borderless,
+    /// Exclusive fullscreen at the desktop's resolution
+    Fullscreen,
+}
+impl WindowMode {
+    fn next(self) -> Self {
+        match self {
+            Self::Windowed => Self::Borderless,
+            Self::Borderless => Self::Fullscreen,
+            Self::Fullscreen => Self::Windowed,
+        }
+    }
+
+    fn previous(self) -> Self {
+        self.next().next()
+    }
+
+    fn apply(self, window: &mut Window) -> Result<(), String> {
+        match self {
+            Self::Windowed => {
+                window.set_fullscreen(FullscreenType::Off)?;
+                window
+                    .set_size(WINDOW_WIDTH, WINDOW_HEIGHT)
+                    .map_err(|e| e.to_string())?;
+                window.set_position(WindowPos::Centered, WindowPos::Centered);
+            }
+            Self::Borderless => window.set_fullscreen(FullscreenType::Desktop)?,
+            Self::Fullscreen => {
+                // Otherwise the display would switch to the window's size
+                let display_mode = window
+                    .subsystem()
+                    .desktop_display_mode(window.display_index()?)?;
+                window.set_display_mode(display_mode)?;
+                window.set_fullscreen(FullscreenType::True)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 // TODO: Add tickrate
 #[allow(unused)]
 struct AppConfig {
     max_fps: Option<u8>,
     show_fps_counter: bool,
+    render_path: RenderPath,
+    /// Fraction of the window's resolution the view is rendered at, upscaled to fill the window
+    render_scale: f64,
+    /// Adjusts `render_scale` to hold a frame rate when set
+    dynamic_resolution: Option<DynamicResolution>,
+    window_mode: WindowMode,
 }
 #[allow(dead_code)]
 impl AppConfig {
@@ -44,10 +150,26 @@ impl AppConfig {
         self.show_fps_counter = show_fps_counter;
         self
     }
+    pub fn set_render_path(mut self, render_path: RenderPath) -> Self {
+        self.render_path = render_path;
+        self
+    }
+    pub fn set_render_scale(mut self, render_scale: f64) -> Self {
+        self.render_scale = render_scale.clamp(MIN_RENDER_SCALE, 1.);
+        self
+    }
+    pub fn set_window_mode(mut self, window_mode: WindowMode) -> Self {
+        self.window_mode = window_mode;
+        self
+    }
+    /// Lower the render scale as needed to hold `target_fps`
+    pub fn dynamic_resolution(mut self, target_fps: f64) -> Self {
+        self.dynamic_resolution = Some(DynamicResolution::new(1. / target_fps));
+        self
+    }
     fn wait_for_frame(&self, start: &Instant) {
         if let Some(fps) = self.max_fps {
-            let time = (1. / fps as f32 * 1000000.).round() as u128;
-            while start.elapsed().as_micros() < time {}
+            wait_for_fps(start, fps as u32);
         }
     }
 }
@@ -56,6 +178,10 @@ impl Default for AppConfig {
         Self {
             max_fps: Some(60),
             show_fps_counter: true,
+            render_path: RenderPath::Canvas,
+            render_scale: 1.,
+            dynamic_resolution: None,
+            window_mode: WindowMode::Borderless,
         }
     }
 }
@@ -64,8 +190,21 @@ impl Default for AppConfig {
 pub struct App {
     canvas: Canvas<Window>,
     event_pump: EventPump,
+    mouse: MouseUtil,
     state: State,
     cfg: AppConfig,
+    debug_overlay: DebugOverlay,
+    /// Size of the window in pixels, which can differ from its size in screen coordinates on high DPI displays
+    window_size: (u32, u32),
+    console: Console,
+    /// Set when another map is loaded, for its sky to be uploaded
+    map_changed: bool,
+    /// How much to supersample a screenshot taken at the end of this frame by, 1 reading back the canvas
+    screenshot: Option<u32>,
+    recording: Option<Recording>,
+    replay: Option<ReplayMode>,
+    game_state: GameState,
+    menu: Menu,
 }
 impl App {
     pub fn new() -> Result<Self, String> {
@@ -73,117 +212,578 @@ impl App {
         let video_subsystem = sdl_context.video()?;
         let event_pump = sdl_context.event_pump()?;
 
-        sdl_context.mouse().set_relative_mouse_mode(true);
+        let mouse = sdl_context.mouse();
+        // Captured once playing
+        mouse.set_relative_mouse_mode(false);
+
+        let cfg = AppConfig::default().set_max_fps(144); // .show_fps_counter(false),
 
-        let window = video_subsystem
+        let mut window = video_subsystem
             .window("raycasting", WINDOW_WIDTH, WINDOW_HEIGHT)
             .position_centered()
-            .fullscreen()
+            .resizable()
+            .allow_highdpi()
             .opengl()
             .build()
             .map_err(|e| e.to_string())?;
+        cfg.window_mode.apply(&mut window)?;
+
+        let state = State::new(Viewport::new(WINDOW_WIDTH, WINDOW_HEIGHT));
+        set_title(&mut window, &state)?;
 
         let mut canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        if let Err(e) = canvas::load_font(FONT_PATH, FONT_POINT_SIZE) {
+            eprintln!("Couldn't load {}, using the bitmap font: {}", FONT_PATH, e);
+        }
 
+        // Translucent walls are drawn with alpha
+        canvas.set_blend_mode(BlendMode::Blend);
         canvas.set_draw_color(Color::BLACK);
         canvas.clear();
         canvas.present();
-
-        let state = State::new();
+        let window_size = canvas.output_size()?;
 
         Ok(Self {
             canvas,
             event_pump,
+            mouse,
             state,
-            cfg: AppConfig::default().set_max_fps(144), // .show_fps_counter(false),
+            cfg,
+            debug_overlay: DebugOverlay::default(),
+            window_size,
+            console: Console::default(),
+            map_changed: false,
+            screenshot: None,
+            recording: None,
+            replay: None,
+            game_state: GameState::MainMenu,
+            menu: Menu::default(),
         })
     }
 
-    fn handle_events(&mut self) -> ControlFlow {
-        for event in self.event_pump.poll_iter() {
+    /// Switch to the map at `path`, starting over at its spawn. Not while a replay's recording or playing, as it
+    /// has to stay on its map
+    fn load_map(&mut self, path: &str) -> Result<(), String> {
+        if self.replay.is_some() {
+            return Err("Stop the replay before changing map".to_owned());
+        }
+        self.state.load_map(path).map_err(|e| e.to_string())?;
+        set_title(self.canvas.window_mut(), &self.state)?;
+        self.map_changed = true;
+        Ok(())
+    }
+
+    fn handle_events(&mut self) -> Result<ControlFlow, String> {
+        // Collected first, as running console commands needs the whole of `self`
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            // Kept up to date whatever the event goes on to, the console and menus included.
+            // The view is recast at the new size next frame
+            if let Event::Window {
+                win_event: WindowEvent::SizeChanged(..),
+                ..
+            } = event
+            {
+                self.window_size = self.canvas.output_size()?;
+            }
+
+            if self.console.open {
+                if self.handle_console_event(event) == ControlFlow::Break {
+                    return Ok(ControlFlow::Break);
+                }
+                continue;
+            }
+            if self.game_state != GameState::Playing {
+                if self.handle_menu_event(event) == ControlFlow::Break {
+                    return Ok(ControlFlow::Break);
+                }
+                continue;
+            }
+
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
+                // Held keys are let go, so the player doesn't keep walking while typing
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backquote),
+                    repeat: false,
+                    ..
+                } => {
+                    self.console.open = true;
+                    self.state.keys = KeyStateHandler::new();
+                }
+
+                Event::Quit { .. } => return Ok(ControlFlow::Break),
+
+                Event::KeyDown {
                     keycode: Some(Keycode::Escape),
+                    repeat: false,
+                    ..
+                } => self.set_game_state(GameState::Paused),
+
+                // Cycle window modes
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    let mode = self.cfg.window_mode.next();
+                    if self.set_window_mode(mode) {
+                        self.report(&format!("{:?}", mode));
+                    }
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::E),
+                    repeat: false,
+                    ..
+                } => {
+                    self.state.keys.press(Key::E);
+                    self.interact();
+                }
+
+                // Switch render paths, to compare them on the FPS counter
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    repeat: false,
+                    ..
+                } => {
+                    self.cfg.render_path = self.cfg.render_path.toggled();
+                    self.report(&format!("Rendering with {:?}", self.cfg.render_path));
+                }
+
+                // Render scale, switching off dynamic resolution so it doesn't fight the keys
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Minus | Keycode::Equals)),
+                    ..
+                } => {
+                    let step = if keycode == Keycode::Minus {
+                        -RENDER_SCALE_STEP
+                    } else {
+                        RENDER_SCALE_STEP
+                    };
+                    self.cfg.dynamic_resolution = None;
+                    self.cfg.render_scale =
+                        (self.cfg.render_scale + step).clamp(MIN_RENDER_SCALE, 1.);
+                    self.report(&format!("Render scale {:.2}", self.cfg.render_scale));
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
                     ..
-                } => return ControlFlow::Break,
+                } => {
+                    self.cfg.dynamic_resolution = match self.cfg.dynamic_resolution {
+                        Some(_) => None,
+                        None => Some(DynamicResolution::new(1. / DYNAMIC_RESOLUTION_FPS)),
+                    };
+                    self.report(&format!(
+                        "Dynamic resolution {}",
+                        if self.cfg.dynamic_resolution.is_some() {
+                            "on"
+                        } else {
+                            "off"
+                        }
+                    ));
+                }
+
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::LeftBracket | Keycode::RightBracket)),
+                    ..
+                } => {
+                    let step = if keycode == Keycode::LeftBracket {
+                        -FOV_STEP
+                    } else {
+                        FOV_STEP
+                    };
+                    self.state.adjust_fov(step);
+                    self.report(&format!("{:?}", self.state.fov));
+                }
+
+                // Show the distortion fisheye correction removes
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    repeat: false,
+                    ..
+                } => {
+                    let frame = &mut self.state.frame;
+                    frame.correct_fisheye = !frame.correct_fisheye;
+                    let message = format!(
+                        "Fisheye correction {}",
+                        if frame.correct_fisheye { "on" } else { "off" }
+                    );
+                    self.report(&message);
+                }
+
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::Tab | Keycode::M | Keycode::F5 | Keycode::F6)),
+                    repeat: false,
+                    ..
+                } => {
+                    let minimap = &mut self.state.minimap;
+                    let (name, on) = match keycode {
+                        Keycode::Tab => ("Automap", &mut minimap.automap),
+                        Keycode::M => ("Minimap following the player", &mut minimap.follow_player),
+                        Keycode::F5 => ("Fog of war", &mut minimap.fog_of_war),
+                        _ => ("Minimap ray hits", &mut minimap.show_hits),
+                    };
+                    *on = !*on;
+                    let message = format!("{} {}", name, if *on { "on" } else { "off" });
+                    self.report(&message);
+                }
+
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::PageUp | Keycode::PageDown)),
+                    ..
+                } => {
+                    let factor = if keycode == Keycode::PageUp {
+                        MINIMAP_ZOOM_STEP
+                    } else {
+                        1. / MINIMAP_ZOOM_STEP
+                    };
+                    self.state.minimap.zoom_by(factor);
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => self.screenshot = Some(1),
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    repeat: false,
+                    ..
+                } => {
+                    let toggled = match self.replay {
+                        Some(_) => self.stop_replay(),
+                        None => self.record_replay(),
+                    };
+                    match toggled {
+                        Ok(message) | Err(message) => self.report(&message),
+                    }
+                }
+
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => {
+                    let toggled = match self.recording {
+                        Some(_) => self.stop_recording(),
+                        None => self.start_recording(RecordingFormat::Y4m, DEFAULT_RECORDING_FPS),
+                    };
+                    match toggled {
+                        Ok(message) | Err(message) => self.report(&message),
+                    }
+                }
+
+                // The mouse is let go while the debug overlay's open, to point at columns with
+                Event::KeyDown {
+                    keycode: Some(Keycode::F1),
+                    repeat: false,
+                    ..
+                } => {
+                    let overlay = &mut self.debug_overlay;
+                    overlay.enabled = !overlay.enabled;
+                    self.mouse.set_relative_mouse_mode(!overlay.enabled);
+                }
 
                 Event::KeyUp {
                     keycode: Some(keycode),
                     ..
-                } => self.state.keys.release(keycode),
+                } => {
+                    if let Some(key) = Key::from_keycode(keycode) {
+                        self.state.keys.release(key);
+                    }
+                }
 
                 Event::KeyDown {
                     keycode: Some(keycode),
                     ..
-                } => self.state.keys.press(keycode),
+                } => {
+                    if let Some(key) = Key::from_keycode(keycode) {
+                        self.state.keys.press(key);
+                    }
+                }
 
-                Event::MouseMotion { xrel, .. } => self.state.mouse_motion(xrel),
+                Event::MouseMotion { x, y, .. } if self.debug_overlay.enabled => {
+                    let (x, y) = self.mouse_to_render(x, y);
+                    self.debug_overlay.mouse_moved(x, y);
+                }
+                Event::MouseMotion { xrel, .. } => self.mouse_motion(xrel),
 
                 _ => {}
             }
         }
 
+        Ok(ControlFlow::Continue)
+    }
+
+    /// Type into the console and run what's entered, instead of playing
+    fn handle_console_event(&mut self, event: Event) -> ControlFlow {
+        match event {
+            Event::Quit { .. } => return ControlFlow::Break,
+
+            Event::KeyDown {
+                keycode: Some(Keycode::Backquote | Keycode::Escape),
+                repeat: false,
+                ..
+            } => self.console.open = false,
+
+            Event::TextInput { text, .. } => self.console.type_text(&text),
+
+            Event::KeyDown {
+                keycode: Some(Keycode::Return | Keycode::KpEnter),
+                ..
+            } => {
+                let line = self.console.submit();
+                match commands::execute(self, &line) {
+                    Ok(Some(output)) => self.console.print(&output),
+                    Ok(None) => {}
+                    Err(e) => self.console.print(&e),
+                }
+            }
+
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Backspace => self.console.backspace(),
+                Keycode::Up => self.console.history_back(),
+                Keycode::Down => self.console.history_forward(),
+                Keycode::Tab => self.console.complete(commands::completions),
+                _ => {}
+            },
+
+            _ => {}
+        }
+
         ControlFlow::Continue
     }
 
-    fn draw_fps(&mut self, fps: f64) -> Result<(), String> {
-        let font_context = ttf::init().unwrap();
-        let mut font = font_context.load_font("./assets/font/Pixeboy.ttf", 20)?;
-        font.set_style(FontStyle::NORMAL);
+    /// Tell the player something, on the console and standard output
+    fn report(&mut self, message: &str) {
+        println!("{}", message);
+        self.console.print(message);
+    }
 
-        let fps_str = &format!("{:.0}", fps);
-        let fs = font.size_of(fps_str).unwrap();
-        self.canvas.set_draw_color(Color::BLACK);
+    /// Pixels drawn per unit of mouse position, across and down. Mouse positions are in screen coordinates, which
+    /// high DPI displays have fewer of than pixels
+    fn mouse_scale(&self) -> (f64, f64) {
+        let (window_width, window_height) = self.canvas.window().size();
+        (
+            self.window_size.0 as f64 / window_width.max(1) as f64,
+            self.window_size.1 as f64 / window_height.max(1) as f64,
+        )
+    }
 
-        self.canvas.fill_rect(Rect::new(
-            (WINDOW_WIDTH - fs.0 * 4) as i32,
-            0,
-            fs.0 * 6,
-            fs.1 * 5,
-        ))?;
+    /// The pixel a mouse position at `(x, y)` is over, in what's drawn
+    fn mouse_to_render(&self, x: i32, y: i32) -> (i32, i32) {
+        let (scale_x, scale_y) = self.mouse_scale();
+        ((x as f64 * scale_x) as i32, (y as f64 * scale_y) as i32)
+    }
 
-        let texture_creator = self.canvas.texture_creator();
+    /// Switch the window to `mode`, returning whether it could. When it can't the window goes back to the mode it
+    /// was in and the player is told why
+    fn set_window_mode(&mut self, mode: WindowMode) -> bool {
+        if let Err(e) = mode.apply(self.canvas.window_mut()) {
+            // Part of the new mode may have been applied before it failed
+            let previous = self.cfg.window_mode;
+            let reverted = previous.apply(self.canvas.window_mut());
+            self.report(&format!("Couldn't switch to {:?}: {}", mode, e));
+            if let Err(e) = reverted {
+                self.report(&format!("Couldn't go back to {:?} either: {}", previous, e));
+            }
+            return false;
+        }
+
+        self.cfg.window_mode = mode;
+        true
+    }
 
-        self.canvas.copy(
-            &font
-                .render(fps_str)
-                .solid(Color::WHITE)
-                .unwrap()
-                .as_texture(&texture_creator)
-                .unwrap(),
-            None,
-            Some(Rect::new(
-                (WINDOW_WIDTH - (fs.0 as f32 * 3.25) as u32) as i32,
-                fs.1 as i32,
-                fs.0 * 3,
-                fs.1 * 3,
-            )),
+    /// What the view is cast and drawn at, for the current render path and scale
+    fn viewport(&self) -> Viewport {
+        let scale = self.cfg.render_scale;
+        let (width, height) = self.window_size;
+        match self.cfg.render_path {
+            // Fewer, wider columns straight onto the window
+            RenderPath::Canvas => Viewport::scaled(width, height, scale),
+            // A smaller framebuffer, stretched over the window when it's presented
+            RenderPath::Framebuffer => {
+                let scaled = |length: u32| ((length as f64 * scale).round() as u32).max(1);
+                Viewport::new(scaled(width), scaled(height))
+            }
+        }
+    }
+
+    fn draw_fps(&mut self, fps: f64) -> Result<(), String> {
+        const SCALE: u32 = 4;
+        const PADDING: u32 = 3 * SCALE;
+
+        let fps_str = format!("{:.0}", fps);
+        let (width, height) = self.canvas.text_size(&fps_str, SCALE);
+        let x = self.window_size.0 as i32 - (width + PADDING * 2) as i32;
+
+        Renderer::fill_rect(
+            &mut self.canvas,
+            render::Rect::new(x, 0, width + PADDING * 2, height + PADDING * 2),
+            raycast::Color::BLACK,
         )?;
-        Ok(())
+        self.canvas.draw_text(
+            &fps_str,
+            x + PADDING as i32,
+            PADDING as i32,
+            SCALE,
+            raycast::Color::WHITE,
+        )
     }
 
     pub fn start(mut self) -> Result<(), String> {
+        let texture_creator = self.canvas.texture_creator();
+        let mut sky = match &self.state.map.sky {
+            Some(sky) => Some(canvas::texture_from_image(&texture_creator, sky)?),
+            None => None,
+        };
+
+        let viewport = self.viewport();
+        let mut framebuffer = Framebuffer::new(viewport.width, viewport.height);
+        let mut streaming =
+            StreamingTexture::new(&texture_creator, viewport.width, viewport.height)?;
+
+        let mut last_frame = Instant::now();
+
         'running: loop {
             let start = Instant::now();
+            // Recordings play back at their own frame rate, so time moves on by one of their frames at a time
+            let dt = match &self.recording {
+                Some(recording) => 1. / recording.fps as f64,
+                None => start.duration_since(last_frame).as_secs_f64(),
+            };
+            last_frame = start;
 
-            if self.handle_events() == ControlFlow::Break {
+            if self.handle_events()? == ControlFlow::Break {
                 break 'running;
             };
+            let events_done = Instant::now();
+
+            if self.map_changed {
+                sky = match &self.state.map.sky {
+                    Some(sky) => Some(canvas::texture_from_image(&texture_creator, sky)?),
+                    None => None,
+                };
+                self.map_changed = false;
+            }
+
+            self.state.frame.viewport = self.viewport();
+            // The game stands still behind the menus
+            if self.game_state == GameState::Playing {
+                self.update(dt);
+            }
+            let update_done = Instant::now();
+            self.state.cast();
+            let cast_done = Instant::now();
+
+            match self.cfg.render_path {
+                RenderPath::Canvas => {
+                    self.canvas.set_draw_color(Color::BLACK);
+                    self.canvas.clear();
+                    self.state.draw(&mut self.canvas, sky.as_ref())?;
+                }
+                RenderPath::Framebuffer => {
+                    let viewport = self.state.frame.viewport;
+                    if framebuffer.size() != (viewport.width, viewport.height) {
+                        framebuffer = Framebuffer::new(viewport.width, viewport.height);
+                        streaming = StreamingTexture::new(
+                            &texture_creator,
+                            viewport.width,
+                            viewport.height,
+                        )?;
+                    }
+
+                    framebuffer.clear(raycast::Color::BLACK)?;
+                    self.state
+                        .draw(&mut framebuffer, self.state.map.sky.as_ref())?;
+                    streaming.upload(&framebuffer)?;
+                    streaming.present(&mut self.canvas)?;
+                }
+            }
+            self.state.minimap.highlighted_column = if self.debug_overlay.enabled {
+                self.debug_overlay
+                    .hovered_column(&self.state, self.window_size.0)
+            } else {
+                None
+            };
+            self.state.draw_overlay(&mut self.canvas)?;
 
-            self.state.update();
+            let render_done = Instant::now();
+            self.debug_overlay.record(FrameTimings {
+                events: events_done - start,
+                update: update_done - events_done,
+                cast: cast_done - update_done,
+                render: render_done - cast_done,
+            });
+            if self.debug_overlay.enabled {
+                self.debug_overlay.draw(&mut self.canvas, &self.state)?;
+            }
+            if self.run_menu()? == ControlFlow::Break {
+                break 'running;
+            }
+            // Before the console and FPS counter are drawn, leaving them out
+            if let Some(supersample) = self.screenshot.take() {
+                let saved = if supersample > 1 {
+                    self.save_supersampled(supersample)
+                } else {
+                    self.save_canvas()
+                };
+                let message = match saved {
+                    Ok(path) => format!("Saved screenshot to {}", path),
+                    Err(e) => format!("Couldn't save screenshot: {}", e),
+                };
+                self.report(&message);
+            }
+            self.record_frame();
+            if self.console.open {
+                self.console.draw(&mut self.canvas)?;
+            }
 
-            self.canvas.set_draw_color(Color::BLACK);
-            self.canvas.clear();
-            self.state.draw(&mut self.canvas)?;
+            if let Some(dynamic_resolution) = &mut self.cfg.dynamic_resolution {
+                self.cfg.render_scale = dynamic_resolution.update(start.elapsed().as_secs_f64());
+            }
 
-            self.cfg.wait_for_frame(&start);
+            // Recordings advance a frame of theirs every loop, so the loop keeps to their frame rate to play at
+            // the speed they'll be watched at
+            match &self.recording {
+                Some(recording) => wait_for_fps(&start, recording.fps),
+                None => self.cfg.wait_for_frame(&start),
+            }
             if self.cfg.show_fps_counter {
                 let fps = 1.0 / start.elapsed().as_secs_f64();
                 self.draw_fps(fps)?;
             }
             self.canvas.present();
         }
+
+        if self.recording.is_some() {
+            println!("{}", self.stop_recording()?);
+        }
+        if let Some(ReplayMode::Recording { .. }) = self.replay {
+            println!("{}", self.stop_replay()?);
+        }
         Ok(())
     }
 }
+
+/// Wait until a frame that began at `start` has lasted one frame at `fps`
+fn wait_for_fps(start: &Instant, fps: u32) {
+    let time = (1. / fps as f32 * 1000000.).round() as u128;
+    while start.elapsed().as_micros() < time {}
+}
+
+/// Name the window after the map being played
+fn set_title(window: &mut Window, state: &State) -> Result<(), String> {
+    let title = match &state.map.meta.name {
+        Some(name) => format!("raycasting - {}", name),
+        None => "raycasting".to_owned(),
+    };
+    window.set_title(&title).map_err(|e| e.to_string())
+}
@@ -0,0 +1,334 @@
+//! What can be run from the console
+
+use std::fs;
+
+use raycast::{math::vector::Vec2, render::Fov};
+
+use super::{
+    recording::{RecordingFormat, DEFAULT_RECORDING_FPS},
+    replays::REPLAYS_DIR,
+    screenshot::MAX_SUPERSAMPLE,
+    App, MIN_RENDER_SCALE,
+};
+use crate::state::{MOUSE_SENSITIVITY_RANGE, MOVE_SPEED_RANGE};
+
+pub const MAPS_DIR: &str = "./assets/maps";
+
+type CommandResult = Result<Option<String>, String>;
+
+struct Command {
+    name: &'static str,
+    /// Arguments it takes, for `help`
+    usage: &'static str,
+    help: &'static str,
+    run: fn(&mut App, &[&str]) -> CommandResult,
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "",
+        help: "List commands",
+        run: help,
+    },
+    Command {
+        name: "map",
+        usage: "<path>",
+        help: "Load a map, starting at its spawn",
+        run: map,
+    },
+    Command {
+        name: "tp",
+        usage: "<x> <y>",
+        help: "Teleport to a point on the map, kept away from its edges",
+        run: tp,
+    },
+    Command {
+        name: "fov",
+        usage: "<degrees> [h|v]",
+        help: "Set the field of view, across the width or height if given, otherwise whichever it is now",
+        run: fov,
+    },
+    Command {
+        name: "noclip",
+        usage: "",
+        help: "Toggle moving through walls",
+        run: noclip,
+    },
+    Command {
+        name: "fps_max",
+        usage: "<fps>",
+        help: "Limit the frame rate, 0 for no limit",
+        run: fps_max,
+    },
+    Command {
+        name: "screenshot",
+        usage: "[<supersample>]",
+        help: "Save the screen to a PNG, recast at a multiple of its resolution if given",
+        run: screenshot,
+    },
+    Command {
+        name: "record",
+        usage: "[stop | y4m | png] [<fps>]",
+        help: "Record every frame to a Y4M video or numbered PNGs, the game running at the recording's rate",
+        run: record,
+    },
+    Command {
+        name: "replay",
+        usage: "record | stop | play <path>",
+        help: "Record input from the start of the map, or play a recording back",
+        run: replay,
+    },
+    Command {
+        name: "set",
+        usage: "[<cvar> <value>]",
+        help: "Change a setting, or list them all",
+        run: set,
+    },
+];
+
+/// A setting `set` can change
+struct Cvar {
+    name: &'static str,
+    /// Lowest and highest values it can be set to, others being clamped into it
+    range: (f64, f64),
+    get: fn(&App) -> f64,
+    set: fn(&mut App, f64),
+}
+
+const CVARS: &[Cvar] = &[
+    Cvar {
+        name: "mouse_sensitivity",
+        range: MOUSE_SENSITIVITY_RANGE,
+        get: |app| app.state.settings.mouse_sensitivity,
+        set: |app, value| app.state.settings.mouse_sensitivity = value,
+    },
+    Cvar {
+        name: "move_speed",
+        range: MOVE_SPEED_RANGE,
+        get: |app| app.state.settings.move_speed,
+        set: |app, value| app.state.settings.move_speed = value,
+    },
+    Cvar {
+        name: "render_scale",
+        range: (MIN_RENDER_SCALE, 1.),
+        get: |app| app.cfg.render_scale,
+        set: |app, value| {
+            // Dynamic resolution would change it straight back
+            app.cfg.dynamic_resolution = None;
+            app.cfg.render_scale = value;
+        },
+    },
+];
+
+/// Run a line entered into the console, returning what to print
+pub fn execute(app: &mut App, line: &str) -> CommandResult {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, args) = match words.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+
+    let command = COMMANDS
+        .iter()
+        .find(|command| command.name == *name)
+        .ok_or_else(|| format!("Unknown command \"{}\", try help", name))?;
+    (command.run)(app, args)
+}
+
+/// Everything the word after `words` could be
+pub fn completions(words: &[&str]) -> Vec<String> {
+    match words {
+        [] => COMMANDS
+            .iter()
+            .map(|command| command.name.to_owned())
+            .collect(),
+        ["record"] => ["stop", "y4m", "png"]
+            .iter()
+            .map(|&word| word.to_owned())
+            .collect(),
+        ["fov", _] => vec!["h".to_owned(), "v".to_owned()],
+        ["set"] => CVARS.iter().map(|cvar| cvar.name.to_owned()).collect(),
+        ["map"] => json_files(MAPS_DIR),
+        ["replay"] => ["record", "stop", "play"]
+            .iter()
+            .map(|&word| word.to_owned())
+            .collect(),
+        ["replay", "play"] => json_files(REPLAYS_DIR),
+        _ => vec![],
+    }
+}
+
+/// Paths of the JSON files in `dir`
+pub fn json_files(dir: &str) -> Vec<String> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `args` parsed as exactly `N` finite numbers
+fn numbers<const N: usize>(args: &[&str], usage: &str) -> Result<[f64; N], String> {
+    let usage = || format!("Usage: {}", usage);
+    if args.len() != N {
+        return Err(usage());
+    }
+
+    let mut numbers = [0.; N];
+    for (number, arg) in numbers.iter_mut().zip(args) {
+        *number = finite(arg).ok_or_else(usage)?;
+    }
+    Ok(numbers)
+}
+
+/// `arg` as a number, unless it isn't one or is infinite or NaN, which `parse` accepts
+fn finite(arg: &str) -> Option<f64> {
+    arg.parse().ok().filter(|number: &f64| number.is_finite())
+}
+
+fn help(_: &mut App, _: &[&str]) -> CommandResult {
+    let lines: Vec<String> = COMMANDS
+        .iter()
+        .map(|command| format!("{} {} - {}", command.name, command.usage, command.help))
+        .collect();
+    Ok(Some(lines.join("\n")))
+}
+
+fn map(app: &mut App, args: &[&str]) -> CommandResult {
+    let path = match args {
+        [path] => path,
+        _ => return Err("Usage: map <path>".to_owned()),
+    };
+    app.load_map(path)?;
+    Ok(Some(format!("Loaded {}", path)))
+}
+
+fn tp(app: &mut App, args: &[&str]) -> CommandResult {
+    let [x, y] = numbers(args, "tp <x> <y>")?;
+    let point = Vec2::new(x, y);
+    let position = app.teleport(point)?;
+    if position == point {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "Kept inside the map at {:.0} {:.0}",
+        position.x, position.y
+    )))
+}
+
+fn fov(app: &mut App, args: &[&str]) -> CommandResult {
+    const USAGE: &str = "fov <degrees> [h|v]";
+    let (degrees, axis) = match *args {
+        [degrees] => (degrees, None),
+        [degrees, axis] => (degrees, Some(axis)),
+        _ => return Err(format!("Usage: {}", USAGE)),
+    };
+    let [degrees] = numbers(&[degrees], USAGE)?;
+    let state = &mut app.state;
+    state.fov = match axis {
+        None => state.fov,
+        Some("h") => Fov::Horizontal(degrees),
+        Some("v") => Fov::Vertical(degrees),
+        Some(_) => return Err(format!("Usage: {}", USAGE)),
+    };
+    state.set_fov(|_| degrees);
+    Ok(Some(format!("{:?}", app.state.fov)))
+}
+
+fn noclip(app: &mut App, _: &[&str]) -> CommandResult {
+    let state = &mut app.state;
+    state.noclip = !state.noclip;
+    Ok(Some(format!(
+        "Noclip {}",
+        if state.noclip { "on" } else { "off" }
+    )))
+}
+
+fn fps_max(app: &mut App, args: &[&str]) -> CommandResult {
+    let [fps] = numbers(args, "fps_max <fps>")?;
+    app.cfg.max_fps = match fps.round() as i64 {
+        0 => None,
+        fps @ 1..=255 => Some(fps as u8),
+        _ => return Err("The frame rate limit has to be from 1 to 255, or 0 for none".to_owned()),
+    };
+    Ok(None)
+}
+
+fn screenshot(app: &mut App, args: &[&str]) -> CommandResult {
+    let supersample = match args {
+        [] => 1,
+        [supersample] => supersample
+            .parse()
+            .ok()
+            .filter(|supersample| (1..=MAX_SUPERSAMPLE).contains(supersample))
+            .ok_or_else(|| format!("Supersampling has to be from 1 to {}", MAX_SUPERSAMPLE))?,
+        _ => return Err("Usage: screenshot [<supersample>]".to_owned()),
+    };
+    // Taken once the frame's drawn, without the console over it
+    app.console.open = false;
+    app.screenshot = Some(supersample);
+    Ok(None)
+}
+
+fn record(app: &mut App, args: &[&str]) -> CommandResult {
+    let (format, fps) = match args {
+        [] if app.recording.is_some() => return app.stop_recording().map(Some),
+        ["stop"] => return app.stop_recording().map(Some),
+        [] => (RecordingFormat::Y4m, DEFAULT_RECORDING_FPS),
+        [format, fps @ ..] if fps.len() <= 1 => {
+            let format = match *format {
+                "y4m" => RecordingFormat::Y4m,
+                "png" => RecordingFormat::Png,
+                _ => return Err(format!("Unknown format \"{}\", try y4m or png", format)),
+            };
+            let fps = match fps {
+                [fps] => fps
+                    .parse()
+                    .ok()
+                    .filter(|&fps| fps > 0)
+                    .ok_or_else(|| format!("\"{}\" isn't a frame rate", fps))?,
+                _ => DEFAULT_RECORDING_FPS,
+            };
+            (format, fps)
+        }
+        _ => return Err("Usage: record [stop | y4m | png] [<fps>]".to_owned()),
+    };
+    app.start_recording(format, fps).map(Some)
+}
+
+fn replay(app: &mut App, args: &[&str]) -> CommandResult {
+    match args {
+        ["record"] => app.record_replay(),
+        ["stop"] => app.stop_replay(),
+        ["play", path] => app.play_replay(path),
+        _ => Err("Usage: replay record | stop | play <path>".to_owned()),
+    }
+    .map(Some)
+}
+
+fn set(app: &mut App, args: &[&str]) -> CommandResult {
+    let (name, value) = match args {
+        [] => {
+            let lines: Vec<String> = CVARS
+                .iter()
+                .map(|cvar| format!("{} {}", cvar.name, (cvar.get)(app)))
+                .collect();
+            return Ok(Some(lines.join("\n")));
+        }
+        [name, value] => (name, value),
+        _ => return Err("Usage: set [<cvar> <value>]".to_owned()),
+    };
+
+    let cvar = CVARS
+        .iter()
+        .find(|cvar| cvar.name == *name)
+        .ok_or_else(|| format!("Unknown cvar \"{}\", try set", name))?;
+    let value = finite(value).ok_or_else(|| format!("\"{}\" isn't a number", value))?;
+    (cvar.set)(app, value.clamp(cvar.range.0, cvar.range.1));
+    Ok(Some(format!("{} {}", cvar.name, (cvar.get)(app))))
+}
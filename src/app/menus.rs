@@ -0,0 +1,216 @@
+//! The screens around playing: the main menu, pausing, settings and picking a map
+
+use std::path::Path;
+
+use sdl2::{event::Event, keyboard::Keycode};
+
+use raycast::render::{DynamicResolution, Fov};
+
+use super::{
+    commands::{self, MAPS_DIR},
+    App, ControlFlow, DYNAMIC_RESOLUTION_FPS, MIN_RENDER_SCALE, RENDER_SCALE_STEP,
+};
+use crate::{
+    key_state_handler::KeyStateHandler,
+    state::{FOV_RANGE, MOUSE_SENSITIVITY_RANGE, MOVE_SPEED_RANGE},
+};
+
+/// Which screen the game's on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    MainMenu,
+    Playing,
+    Paused,
+    /// `in_game` when opened from the pause menu, so going back returns there rather than to the main menu
+    Settings {
+        in_game: bool,
+    },
+    MapSelect {
+        in_game: bool,
+    },
+}
+impl GameState {
+    /// Where Escape or a Back item leads
+    fn back(self) -> Self {
+        match self {
+            Self::MainMenu => Self::MainMenu,
+            Self::Playing => Self::Paused,
+            Self::Paused => Self::Playing,
+            Self::Settings { in_game } | Self::MapSelect { in_game } => {
+                if in_game {
+                    Self::Paused
+                } else {
+                    Self::MainMenu
+                }
+            }
+        }
+    }
+}
+
+impl App {
+    /// Move to another screen, only capturing the mouse while playing
+    pub(super) fn set_game_state(&mut self, game_state: GameState) {
+        if self.game_state == GameState::Playing && game_state != GameState::Playing {
+            // Keys let go of in the menu would otherwise stay held
+            self.state.keys = KeyStateHandler::new();
+        }
+        self.game_state = game_state;
+        self.menu.reset();
+        self.mouse.set_relative_mouse_mode(
+            game_state == GameState::Playing && !self.debug_overlay.enabled,
+        );
+    }
+
+    pub(super) fn handle_menu_event(&mut self, event: Event) -> ControlFlow {
+        match event {
+            Event::Quit { .. } => return ControlFlow::Break,
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                repeat: false,
+                ..
+            } => self.set_game_state(self.game_state.back()),
+            event => self.menu.handle_event(&event, self.mouse_scale()),
+        }
+        ControlFlow::Continue
+    }
+
+    /// Lay out, act on and draw the menu for the current screen, over whatever's been drawn already
+    pub(super) fn run_menu(&mut self) -> Result<ControlFlow, String> {
+        let mut next = None;
+        match self.game_state {
+            GameState::Playing => return Ok(ControlFlow::Continue),
+            GameState::MainMenu => {
+                self.menu.begin("raycast");
+                if self.menu.button("Play") {
+                    next = Some(GameState::Playing);
+                }
+                if self.menu.button("Select map") {
+                    next = Some(GameState::MapSelect { in_game: false });
+                }
+                if self.menu.button("Settings") {
+                    next = Some(GameState::Settings { in_game: false });
+                }
+                if self.menu.button("Quit") {
+                    return Ok(ControlFlow::Break);
+                }
+            }
+            GameState::Paused => {
+                self.menu.begin("Paused");
+                if self.menu.button("Resume") {
+                    next = Some(GameState::Playing);
+                }
+                if self.menu.button("Select map") {
+                    next = Some(GameState::MapSelect { in_game: true });
+                }
+                if self.menu.button("Settings") {
+                    next = Some(GameState::Settings { in_game: true });
+                }
+                if self.menu.button("Main menu") {
+                    next = Some(GameState::MainMenu);
+                }
+                if self.menu.button("Quit") {
+                    return Ok(ControlFlow::Break);
+                }
+            }
+            GameState::Settings { .. } => {
+                self.settings_menu()?;
+                if self.menu.button("Back") {
+                    next = Some(self.game_state.back());
+                }
+            }
+            GameState::MapSelect { .. } => {
+                self.menu.begin("Select map");
+                for path in commands::json_files(MAPS_DIR) {
+                    let name = Path::new(&path).file_stem().map_or(path.clone(), |stem| {
+                        stem.to_string_lossy().replace('_', " ")
+                    });
+                    if self.menu.button(&name) {
+                        match self.load_map(&path) {
+                            Ok(()) => next = Some(GameState::Playing),
+                            Err(e) => self.report(&format!("Couldn't load {}: {}", path, e)),
+                        }
+                    }
+                }
+                if self.menu.button("Back") {
+                    next = Some(self.game_state.back());
+                }
+            }
+        }
+
+        self.menu.end(&mut self.canvas)?;
+        if let Some(game_state) = next {
+            self.set_game_state(game_state);
+        }
+        Ok(ControlFlow::Continue)
+    }
+
+    fn settings_menu(&mut self) -> Result<(), String> {
+        self.menu.begin("Settings");
+        let settings = &mut self.state.settings;
+        self.menu.slider(
+            "Mouse sensitivity",
+            &mut settings.mouse_sensitivity,
+            MOUSE_SENSITIVITY_RANGE,
+            0.001,
+            3,
+        );
+        self.menu.slider(
+            "Move speed",
+            &mut settings.move_speed,
+            MOVE_SPEED_RANGE,
+            0.5,
+            1,
+        );
+
+        let (label, mut degrees) = match self.state.fov {
+            Fov::Horizontal(degrees) => ("Horizontal FOV", degrees),
+            Fov::Vertical(degrees) => ("Vertical FOV", degrees),
+        };
+        if self.menu.slider(label, &mut degrees, FOV_RANGE, 5., 0) {
+            self.state.set_fov(|_| degrees);
+        }
+        let axis = match self.state.fov {
+            Fov::Horizontal(_) => "Horizontal",
+            Fov::Vertical(_) => "Vertical",
+        };
+        if self.menu.choice("FOV axis", axis) != 0 {
+            self.state.switch_fov_axis();
+        }
+
+        // Dynamic resolution would change the scale straight back
+        if self.menu.slider(
+            "Render scale",
+            &mut self.cfg.render_scale,
+            (MIN_RENDER_SCALE, 1.),
+            RENDER_SCALE_STEP,
+            2,
+        ) {
+            self.cfg.dynamic_resolution = None;
+        }
+        let mut dynamic_resolution = self.cfg.dynamic_resolution.is_some();
+        if self
+            .menu
+            .toggle("Dynamic resolution", &mut dynamic_resolution)
+        {
+            self.cfg.dynamic_resolution =
+                dynamic_resolution.then(|| DynamicResolution::new(1. / DYNAMIC_RESOLUTION_FPS));
+        }
+
+        self.menu
+            .toggle("Fisheye correction", &mut self.state.frame.correct_fisheye);
+        self.menu
+            .toggle("FPS counter", &mut self.cfg.show_fps_counter);
+
+        let window_mode = format!("{:?}", self.cfg.window_mode);
+        let step = self.menu.choice("Window", &window_mode);
+        if step != 0 {
+            let mode = if step > 0 {
+                self.cfg.window_mode.next()
+            } else {
+                self.cfg.window_mode.previous()
+            };
+            self.set_window_mode(mode);
+        }
+        Ok(())
+    }
+}
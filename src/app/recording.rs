@@ -0,0 +1,129 @@
+//! Recording gameplay a frame at a time, at a fixed rate rather than however fast frames are drawn
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+};
+
+use raycast::{image::Image, render::canvas, video::Y4mWriter};
+
+use super::{screenshot, App};
+
+const RECORDINGS_DIR: &str = "./recordings";
+/// Frame rate recordings are made at when one isn't given
+pub const DEFAULT_RECORDING_FPS: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// A single uncompressed YUV4MPEG2 video
+    Y4m,
+    /// A directory of numbered PNGs
+    Png,
+}
+
+enum Output {
+    Y4m(Y4mWriter<BufWriter<File>>),
+    Png,
+}
+
+pub struct Recording {
+    output: Output,
+    /// Where the frames go, a file or directory depending on the format
+    path: String,
+    /// Frames a second of the recording, the game advancing by a frame's worth of time each frame while it runs
+    pub fps: u32,
+    frames: u32,
+}
+impl Recording {
+    /// Start a recording of `width` by `height` frames, named after the time
+    fn new(format: RecordingFormat, fps: u32, width: u32, height: u32) -> Result<Self, String> {
+        fs::create_dir_all(RECORDINGS_DIR).map_err(|e| e.to_string())?;
+        let name = format!("{}/{}", RECORDINGS_DIR, screenshot::timestamp());
+
+        let (output, path) = match format {
+            RecordingFormat::Y4m => {
+                let path = format!("{}.y4m", name);
+                let file = File::create(&path).map_err(|e| e.to_string())?;
+                let writer = Y4mWriter::new(BufWriter::new(file), width, height, fps)
+                    .map_err(|e| e.to_string())?;
+                (Output::Y4m(writer), path)
+            }
+            RecordingFormat::Png => {
+                fs::create_dir_all(&name).map_err(|e| e.to_string())?;
+                (Output::Png, name)
+            }
+        };
+
+        Ok(Self {
+            output,
+            path,
+            fps,
+            frames: 0,
+        })
+    }
+
+    fn add_frame(&mut self, image: &Image) -> Result<(), String> {
+        match &mut self.output {
+            Output::Y4m(writer) => writer.write_frame(image).map_err(|e| e.to_string())?,
+            Output::Png => image
+                .save_png(format!("{}/{:06}.png", self.path, self.frames))
+                .map_err(|e| e.to_string())?,
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Finish writing, returning what was recorded
+    fn finish(self) -> Result<String, String> {
+        if let Output::Y4m(writer) = self.output {
+            writer.into_inner().flush().map_err(|e| e.to_string())?;
+        }
+        Ok(format!(
+            "Recorded {} frames at {} FPS to {}",
+            self.frames, self.fps, self.path
+        ))
+    }
+}
+
+impl App {
+    pub(super) fn start_recording(
+        &mut self,
+        format: RecordingFormat,
+        fps: u32,
+    ) -> Result<String, String> {
+        if self.recording.is_some() {
+            return Err("Already recording".to_owned());
+        }
+        let (width, height) = self.window_size;
+        let recording = Recording::new(format, fps, width, height)?;
+        let message = format!("Recording to {}", recording.path);
+        self.recording = Some(recording);
+        Ok(message)
+    }
+
+    pub(super) fn stop_recording(&mut self) -> Result<String, String> {
+        match self.recording.take() {
+            Some(recording) => recording.finish(),
+            None => Err("Not recording".to_owned()),
+        }
+    }
+
+    /// Add what's been drawn onto the canvas this frame to the recording, if there is one,
+    /// stopping it if the frame can't be written
+    pub(super) fn record_frame(&mut self) {
+        let recording = match &mut self.recording {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        let recorded =
+            canvas::read_image(&self.canvas).and_then(|image| recording.add_frame(&image));
+        if let Err(e) = recorded {
+            let message = match self.stop_recording() {
+                Ok(message) => format!("Recording stopped: {}. {}", e, message),
+                Err(_) => format!("Recording stopped: {}", e),
+            };
+            self.report(&message);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Recording replays while playing, and playing them back in place of the player's input
+
+use std::{fs, mem};
+
+use raycast::math::vector::Vec2;
+
+use super::{menus::GameState, screenshot, App};
+use crate::{
+    key_state_handler::KeyStateHandler,
+    replay::{Replay, Setup, Tick},
+};
+
+pub const REPLAYS_DIR: &str = "./replays";
+
+pub enum ReplayMode {
+    Recording {
+        replay: Replay,
+        /// Input so far this tick
+        tick: Tick,
+        /// Setup as of the last tick, to notice the player changing it
+        setup: Setup,
+        /// Where it's saved once stopped
+        path: String,
+    },
+    Playing {
+        replay: Replay,
+        /// Index of the tick to play next
+        next: usize,
+    },
+}
+
+impl App {
+    /// Restart the map and record everything the player does from there
+    pub(super) fn record_replay(&mut self) -> Result<String, String> {
+        if self.replay.is_some() {
+            return Err("A replay's already recording or playing".to_owned());
+        }
+        fs::create_dir_all(REPLAYS_DIR).map_err(|e| e.to_string())?;
+        let path = format!("{}/{}.json", REPLAYS_DIR, screenshot::timestamp());
+
+        let replay = Replay::record(&mut self.state)?;
+        self.map_changed = true;
+        let message = format!("Recording a replay to {}", path);
+        self.replay = Some(ReplayMode::Recording {
+            setup: replay.setup,
+            replay,
+            tick: Tick::default(),
+            path,
+        });
+        Ok(message)
+    }
+
+    /// Play the replay at `path` from the start, the player's input being ignored until it ends
+    pub fn play_replay(&mut self, path: &str) -> Result<String, String> {
+        if self.replay.is_some() {
+            return Err("A replay's already recording or playing".to_owned());
+        }
+        let replay = Replay::load(path)?;
+        replay.restart(&mut self.state)?;
+        self.map_changed = true;
+        let message = format!(
+            "Playing {} ticks, {:.1}s, from {}",
+            replay.ticks.len(),
+            replay.duration(),
+            path
+        );
+        self.replay = Some(ReplayMode::Playing { replay, next: 0 });
+        self.set_game_state(GameState::Playing);
+        Ok(message)
+    }
+
+    /// Save the replay being recorded, or stop the one playing
+    pub(super) fn stop_replay(&mut self) -> Result<String, String> {
+        match self.replay.take() {
+            Some(ReplayMode::Recording { replay, path, .. }) => {
+                replay.save(&path)?;
+                Ok(format!(
+                    "Saved {} ticks, {:.1}s, to {}",
+                    replay.ticks.len(),
+                    replay.duration(),
+                    path
+                ))
+            }
+            Some(ReplayMode::Playing { .. }) => {
+                self.state.keys = KeyStateHandler::new();
+                Ok("Stopped the replay".to_owned())
+            }
+            None => Err("No replay recording or playing".to_owned()),
+        }
+    }
+
+    /// Turn by the player moving the mouse, unless a replay's playing
+    pub(super) fn mouse_motion(&mut self, xrel: i32) {
+        match &mut self.replay {
+            Some(ReplayMode::Playing { .. }) => {}
+            Some(ReplayMode::Recording { tick, .. }) => {
+                tick.mouse.push(xrel);
+                self.state.mouse_motion(xrel);
+            }
+            None => self.state.mouse_motion(xrel),
+        }
+    }
+
+    /// Open or close a door for the player, unless a replay's playing
+    pub(super) fn interact(&mut self) {
+        match &mut self.replay {
+            Some(ReplayMode::Playing { .. }) => {}
+            Some(ReplayMode::Recording { tick, .. }) => {
+                tick.interact = true;
+                self.state.interact();
+            }
+            None => self.state.interact(),
+        }
+    }
+
+    /// Teleport the player from the console, unless a replay's playing, returning where they ended up
+    pub(super) fn teleport(&mut self, point: Vec2) -> Result<Vec2, String> {
+        match &mut self.replay {
+            Some(ReplayMode::Playing { .. }) => {
+                Err("Can't teleport while a replay's playing".to_owned())
+            }
+            Some(ReplayMode::Recording { tick, .. }) => {
+                tick.teleport = Some((point.x, point.y));
+                Ok(self.state.teleport(point))
+            }
+            None => Ok(self.state.teleport(point)),
+        }
+    }
+
+    /// Advance the game by `dt` seconds, or by the next tick of the replay playing
+    pub(super) fn update(&mut self, dt: f64) {
+        match &mut self.replay {
+            Some(ReplayMode::Recording {
+                replay,
+                tick,
+                setup,
+                ..
+            }) => {
+                tick.finish(&self.state, setup, dt);
+                self.state.update(dt);
+                replay.ticks.push(mem::take(tick));
+            }
+            Some(ReplayMode::Playing { replay, next }) => match replay.ticks.get(*next) {
+                Some(tick) => {
+                    tick.apply(&mut self.state);
+                    *next += 1;
+                }
+                None => {
+                    self.replay = None;
+                    self.state.keys = KeyStateHandler::new();
+                    self.report("Replay finished");
+                    self.state.update(dt);
+                }
+            },
+            None => self.state.update(dt),
+        }
+    }
+}
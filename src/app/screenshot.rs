@@ -0,0 +1,97 @@
+//! Saving what's on screen to PNGs
+
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use raycast::{
+    image::Image,
+    render::{canvas, Framebuffer, Renderer, Viewport},
+    Color,
+};
+
+use super::App;
+
+const SCREENSHOTS_DIR: &str = "./screenshots";
+/// Most a screenshot can be supersampled by, a 4K window at 4x already being 15360 pixels across
+pub const MAX_SUPERSAMPLE: u32 = 4;
+
+impl App {
+    /// Save whatever's been drawn onto the canvas this frame, returning where it went
+    pub(super) fn save_canvas(&self) -> Result<String, String> {
+        save(&canvas::read_image(&self.canvas)?)
+    }
+
+    /// Recast the view with `supersample` times as many columns and rows as the window has, saving it and the
+    /// minimap drawn over it at that resolution
+    pub(super) fn save_supersampled(&mut self, supersample: u32) -> Result<String, String> {
+        let (width, height) = self.window_size;
+        let viewport = self.state.frame.viewport;
+        self.state.frame.viewport = Viewport::new(width * supersample, height * supersample);
+        self.state.cast();
+
+        let mut framebuffer = Framebuffer::new(width * supersample, height * supersample);
+        let drawn = framebuffer
+            .clear(Color::BLACK)
+            .and_then(|_| {
+                self.state
+                    .draw(&mut framebuffer, self.state.map.sky.as_ref())
+            })
+            .and_then(|_| self.state.draw_overlay(&mut framebuffer));
+
+        // Put back the frame the rest of this one is drawn from
+        self.state.frame.viewport = viewport;
+        self.state.cast();
+
+        drawn?;
+        save(framebuffer.image())
+    }
+}
+
+/// Save `image` to a new file named after the time, returning its path
+fn save(image: &Image) -> Result<String, String> {
+    fs::create_dir_all(SCREENSHOTS_DIR).map_err(|e| e.to_string())?;
+    let path = format!("{}/{}.png", SCREENSHOTS_DIR, timestamp());
+    image.save_png(&path).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// The current UTC time as `YYYY-MM-DD_HH-MM-SS.mmm`, which sorts in the order screenshots were taken
+pub(super) fn timestamp() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}.{:03}",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The date `days` after 1970-01-01, by Howard Hinnant's algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months counted from March, so leap days fall at the end of the year
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
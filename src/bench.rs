@@ -0,0 +1,242 @@
+//! `raycast bench`: fly the camera along a path without a window, timing each stage of every frame
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use raycast::{
+    math::{spline::Spline, vector::Vec2},
+    render::{Framebuffer, Renderer, Viewport},
+    Color,
+};
+
+use crate::{replay::Replay, state::State};
+
+const USAGE: &str = concat!(
+    "Usage: raycast bench [--map <path>] [--frames <count>] [--size <width>x<height>] ",
+    "[--waypoints \"<x>,<y> <x>,<y> ...\" | --replay <path>]"
+);
+const DEFAULT_FRAMES: usize = 1000;
+const DEFAULT_SIZE: (u32, u32) = (1920, 1080);
+/// Frames run before timing starts, so caches and the allocator have settled
+const WARMUP_FRAMES: usize = 10;
+/// Seconds each frame advances the game by on a scripted path
+const DT: f64 = 1. / 60.;
+
+/// Where the camera goes each frame
+enum CameraPath {
+    /// Once round a loop, facing along it
+    Spline(Spline),
+    /// The input of a recorded session, frame by frame
+    Replay(Replay),
+}
+
+/// Milliseconds one stage took across the timed frames
+#[derive(Serialize)]
+struct Stats {
+    mean: f64,
+    min: f64,
+    median: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+}
+impl Stats {
+    fn new(durations: &[Duration]) -> Self {
+        let mut ms: Vec<f64> = durations.iter().map(|d| d.as_secs_f64() * 1000.).collect();
+        ms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let percentile = |p: f64| ms[((ms.len() - 1) as f64 * p).round() as usize];
+        Self {
+            mean: ms.iter().sum::<f64>() / ms.len() as f64,
+            min: ms[0],
+            median: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: ms[ms.len() - 1],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Stages {
+    update: Stats,
+    cast: Stats,
+    render: Stats,
+    /// All three together
+    frame: Stats,
+}
+
+#[derive(Serialize)]
+struct Report {
+    map: String,
+    path: &'static str,
+    width: u32,
+    height: u32,
+    frames: usize,
+    /// Frames a second the mean frame time works out to
+    fps: f64,
+    stages: Stages,
+}
+
+/// Run the benchmark `args` describe, printing the results as JSON
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_options(args)?;
+    let option = |name: &str| -> Option<&str> {
+        options
+            .iter()
+            .find(|(flag, _)| *flag == name)
+            .map(|(_, value)| *value)
+    };
+
+    let (width, height) = match option("--size") {
+        Some(size) => parse_size(size).ok_or_else(|| USAGE.to_owned())?,
+        None => DEFAULT_SIZE,
+    };
+    let viewport = Viewport::new(width, height);
+
+    let (mut state, path) = match option("--replay") {
+        // A replay brings its own map and path
+        Some(_) if option("--map").is_some() || option("--waypoints").is_some() => {
+            return Err(USAGE.to_owned())
+        }
+        Some(path) => {
+            let replay = Replay::load(path)?;
+            let mut state = State::with_map(viewport, &replay.map).map_err(|e| e.to_string())?;
+            replay.setup.apply(&mut state);
+            (state, CameraPath::Replay(replay))
+        }
+        None => {
+            let state = match option("--map") {
+                Some(map) => State::with_map(viewport, map).map_err(|e| e.to_string())?,
+                None => State::new(viewport),
+            };
+            let waypoints = match option("--waypoints") {
+                Some(waypoints) => parse_waypoints(waypoints).ok_or_else(|| USAGE.to_owned())?,
+                None => default_waypoints(state.map.dims),
+            };
+            (state, CameraPath::Spline(Spline::closed(waypoints)))
+        }
+    };
+    let frames = match (&path, option("--frames")) {
+        (_, Some(frames)) => frames
+            .parse()
+            .ok()
+            .filter(|&frames| frames > 0)
+            .ok_or_else(|| USAGE.to_owned())?,
+        (CameraPath::Replay(replay), None) => replay.ticks.len().max(1),
+        (CameraPath::Spline(_), None) => DEFAULT_FRAMES,
+    };
+
+    let mut framebuffer = Framebuffer::new(width, height);
+    let mut timings = vec![];
+    for idx in 0..WARMUP_FRAMES + frames {
+        // Warmup frames all stay on the first
+        let frame = idx.saturating_sub(WARMUP_FRAMES);
+        let start = Instant::now();
+        match &path {
+            CameraPath::Spline(spline) => {
+                state.update(DT);
+                let t = frame as f64 / frames as f64;
+                state.camera.position = spline.point(t);
+                state.camera.angle = spline.tangent(t).angle();
+            }
+            // Past the end, the last tick's input is held
+            CameraPath::Replay(replay) => match replay.ticks.get(frame) {
+                Some(tick) => tick.apply(&mut state),
+                None => state.update(DT),
+            },
+        }
+        let updated = Instant::now();
+        state.cast();
+        let cast = Instant::now();
+        framebuffer.clear(Color::BLACK)?;
+        state.draw(&mut framebuffer, state.map.sky.as_ref())?;
+        let rendered = Instant::now();
+
+        if idx >= WARMUP_FRAMES {
+            timings.push([
+                updated - start,
+                cast - updated,
+                rendered - cast,
+                rendered - start,
+            ]);
+        }
+        // Warming up on a replay mustn't use up its first ticks
+        if idx + 1 == WARMUP_FRAMES {
+            if let CameraPath::Replay(replay) = &path {
+                replay.restart(&mut state)?;
+            }
+        }
+    }
+
+    let stage = |idx: usize| Stats::new(&timings.iter().map(|t| t[idx]).collect::<Vec<_>>());
+    let frame = stage(3);
+    let report = Report {
+        map: state.map_path.clone(),
+        path: match path {
+            CameraPath::Spline(_) => "spline",
+            CameraPath::Replay(_) => "replay",
+        },
+        width,
+        height,
+        frames,
+        fps: 1000. / frame.mean,
+        stages: Stages {
+            update: stage(0),
+            cast: stage(1),
+            render: stage(2),
+            frame,
+        },
+    };
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// `<width>x<height>`
+/// Each `--flag value` pair in `args`, refusing flags the benchmark doesn't know or that are given twice
+fn parse_options(args: &[String]) -> Result<Vec<(&str, &str)>, String> {
+    const FLAGS: [&str; 5] = ["--map", "--frames", "--size", "--waypoints", "--replay"];
+
+    let mut options: Vec<(&str, &str)> = vec![];
+    let mut args = args.iter().map(String::as_str);
+    while let Some(flag) = args.next() {
+        if !FLAGS.contains(&flag) {
+            return Err(format!("Unknown option \"{}\". {}", flag, USAGE));
+        }
+        if options.iter().any(|(given, _)| *given == flag) {
+            return Err(format!("{} given more than once. {}", flag, USAGE));
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value. {}", flag, USAGE))?;
+        options.push((flag, value));
+    }
+    Ok(options)
+}
+
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Space separated `<x>,<y>` points
+fn parse_waypoints(waypoints: &str) -> Option<Vec<Vec2>> {
+    let points: Option<Vec<Vec2>> = waypoints
+        .split_whitespace()
+        .map(|point| {
+            let (x, y) = point.split_once(',')?;
+            Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect();
+    points.filter(|points| !points.is_empty())
+}
+
+/// A loop round the middle of a map `dims` in size, looking over most of it
+fn default_waypoints(dims: (u32, u32)) -> Vec<Vec2> {
+    let (width, height) = (dims.0 as f64, dims.1 as f64);
+    [(0.25, 0.25), (0.75, 0.25), (0.75, 0.75), (0.25, 0.75)]
+        .iter()
+        .map(|&(x, y)| Vec2::new(width * x, height * y))
+        .collect()
+}
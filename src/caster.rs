@@ -0,0 +1,131 @@
+//! Casting single rays through a map's walls, bouncing off mirrors and through portals
+
+use crate::math::{
+    ray::Ray,
+    vector::Vec2,
+    wall::{Surface, Wall},
+};
+
+/// How many mirrors and portals a single ray can pass through before giving up
+pub const MAX_BOUNCES: usize = 8;
+
+/// Where a ray met a wall
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    /// Index into the walls of the wall it hit
+    pub wall_index: usize,
+    /// Where on the wall it hit
+    pub point: Vec2,
+    /// Distance the ray travelled to get here, through any mirrors and portals, not corrected for fisheye
+    pub distance: f64,
+    /// Where the last leg of the ray started, the player or the mirror or portal it came out of
+    pub eye: Vec2,
+}
+
+/// Cast a ray from `origin` heading `angle` radians, filling `hits` with every wall it meets, nearest first.
+/// The ray stops at the first opaque wall of full height and passes through the gaps in grates without hitting them.
+/// Mirrors reflect it and portals carry it on from their target, up to `MAX_BOUNCES` times
+pub fn cast(walls: &[Wall], origin: Vec2, angle: f64, hits: &mut Vec<RayHit>) {
+    hits.clear();
+
+    let mut ray = Ray::from_angle(origin, angle);
+    let mut travelled = 0.;
+    // The wall the ray is leaving, so it doesn't hit it again straight away
+    let mut leaving = None;
+
+    for _ in 0..=MAX_BOUNCES {
+        let leg_start = hits.len();
+        cast_leg(walls, &ray, leaving, travelled, hits);
+
+        let last = match hits[leg_start..].last() {
+            Some(last) => *last,
+            None => return,
+        };
+        let wall = &walls[last.wall_index];
+
+        match wall.surface {
+            Surface::Mirror => {
+                // The mirror stays in the hits so its color tints the reflection
+                let normal = wall.normal();
+                let reflected = ray.dir - normal * (2. * ray.dir.dot(normal));
+
+                ray = Ray::new(last.point, reflected);
+                leaving = Some(last.wall_index);
+            }
+            Surface::Portal { target } => {
+                // Portals are seamless, nothing is drawn for them
+                hits.pop();
+
+                let (exit, exit_angle) =
+                    through_portal(wall, &walls[target], last.point, ray.angle());
+                ray = Ray::from_angle(exit, exit_angle);
+                leaving = Some(target);
+            }
+            _ => return,
+        }
+
+        travelled = last.distance;
+    }
+}
+
+/// Hits along one straight leg of a ray, appended to `hits` nearest first
+fn cast_leg(
+    walls: &[Wall],
+    ray: &Ray,
+    leaving: Option<usize>,
+    travelled: f64,
+    hits: &mut Vec<RayHit>,
+) {
+    let leg_start = hits.len();
+
+    for (wall_index, wall) in walls.iter().enumerate() {
+        if Some(wall_index) == leaving {
+            continue;
+        }
+
+        if let Some(distance) = ray.intersect(&wall.segment()) {
+            let point = ray.at(distance);
+            if wall.surface.is_gap_at(point.distance(wall.a)) {
+                continue;
+            }
+
+            hits.push(RayHit {
+                wall_index,
+                point,
+                distance: travelled + distance,
+                eye: ray.origin,
+            });
+        }
+    }
+
+    hits[leg_start..].sort_by(|a, b| {
+        a.distance
+            .partial_cmp(&b.distance)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    if let Some(first_solid) = hits[leg_start..]
+        .iter()
+        .position(|hit| walls[hit.wall_index].blocks_ray())
+    {
+        hits.truncate(leg_start + first_solid + 1);
+    }
+}
+
+/// Where something at `point` on `source` heading `angle` comes out of `target`, and which way it's heading.
+/// Going into a portal's front face comes out of its target's front face, and the same for the back
+pub fn through_portal(source: &Wall, target: &Wall, point: Vec2, angle: f64) -> (Vec2, f64) {
+    let source_length = source.segment().length();
+    let t = if source_length == 0. {
+        0.
+    } else {
+        point.distance(source.a) / source_length
+    };
+
+    // Walls are mirrored along their length so left and right are kept when stepping through
+    let exit = target.segment().point_at(1. - t);
+
+    let rotation = (-target.normal()).angle() - source.normal().angle();
+
+    (exit, angle + rotation)
+}
@@ -0,0 +1,72 @@
+//! Colors as stored in maps and drawn to the screen
+
+/// An 8 bit per channel color with alpha, 255 being fully opaque
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    /// Red channel
+    pub r: u8,
+    /// Green channel
+    pub g: u8,
+    /// Blue channel
+    pub b: u8,
+    /// Alpha channel, 0 being fully transparent
+    pub a: u8,
+}
+impl Color {
+    /// Opaque black
+    pub const BLACK: Self = Self::rgb(0, 0, 0);
+    /// Opaque white
+    pub const WHITE: Self = Self::rgb(255, 255, 255);
+    /// Pure red
+    pub const RED: Self = Self::rgb(255, 0, 0);
+    /// Pure green
+    pub const GREEN: Self = Self::rgb(0, 255, 0);
+    /// Pure blue
+    pub const BLUE: Self = Self::rgb(0, 0, 255);
+    /// Pure yellow
+    pub const YELLOW: Self = Self::rgb(255, 255, 0);
+    /// Pure cyan
+    pub const CYAN: Self = Self::rgb(0, 255, 255);
+    /// Pure magenta
+    pub const MAGENTA: Self = Self::rgb(255, 0, 255);
+    /// A mid gray
+    pub const GRAY: Self = Self::rgb(128, 128, 128);
+    /// Fully transparent black, drawing nothing
+    pub const TRANSPARENT: Self = Self::rgba(0, 0, 0, 0);
+
+    /// A fully opaque color
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    /// A color with every channel given, alpha included
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Blend toward `other` by `t`, 0 giving `self` and 1 giving `other`. Alpha is kept from `self`
+    pub fn lerp(&self, other: Self, t: f64) -> Self {
+        let t = t.clamp(0., 1.);
+        let channel =
+            |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+        Self::rgba(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+            self.a,
+        )
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<Color> for sdl2::pixels::Color {
+    fn from(this: Color) -> Self {
+        Self::RGBA(this.r, this.g, this.b, this.a)
+    }
+}
+#[cfg(feature = "sdl")]
+impl From<sdl2::pixels::Color> for Color {
+    fn from(this: sdl2::pixels::Color) -> Self {
+        Self::rgba(this.r, this.g, this.b, this.a)
+    }
+}
@@ -0,0 +1,237 @@
+use raycast::{
+    render::{Rect, Renderer},
+    Color,
+};
+
+const TEXT_SCALE: u32 = 2;
+/// Pixels between the text and the edges of the console
+const PADDING: u32 = 8;
+/// Fraction of the screen's height the console drops down over
+const HEIGHT: f64 = 0.4;
+const BACKGROUND: Color = Color::rgba(0, 0, 0, 208);
+const INPUT_COLOR: Color = Color::YELLOW;
+/// Lines of output kept for scrolling back through
+const MAX_OUTPUT: usize = 256;
+
+/// A drop down command line. It only edits and shows text, running what's entered is up to the caller
+#[derive(Debug, Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    /// Lines printed so far, oldest first
+    output: Vec<String>,
+    /// Commands entered so far, oldest first
+    history: Vec<String>,
+    /// Where in `history` the input came from, while going back through it
+    history_position: Option<usize>,
+}
+impl Console {
+    /// Add a line, or several separated by `\n`, to the output
+    pub fn print(&mut self, text: &str) {
+        self.output.extend(text.lines().map(str::to_owned));
+        let excess = self.output.len().saturating_sub(MAX_OUTPUT);
+        self.output.drain(..excess);
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        // The key opening and closing the console types one too
+        self.input.extend(text.chars().filter(|&c| c != '`'));
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Take what's been entered, keeping it in the history and echoing it to the output
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        self.print(&format!("> {}", line));
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.history_position = None;
+        line
+    }
+
+    /// Replace the input with the command before the one it came from in the history
+    pub fn history_back(&mut self) {
+        let position = match self.history_position {
+            Some(position) => position.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        self.history_position = Some(position);
+        self.input = self.history[position].clone();
+    }
+
+    /// Replace the input with the command after the one it came from in the history, or clear it past the end
+    pub fn history_forward(&mut self) {
+        let position = match self.history_position {
+            Some(position) => position + 1,
+            None => return,
+        };
+        if position < self.history.len() {
+            self.history_position = Some(position);
+            self.input = self.history[position].clone();
+        } else {
+            self.history_position = None;
+            self.input.clear();
+        }
+    }
+
+    /// Complete the last word of the input as far as the `candidates` for it agree, listing them when there's
+    /// more than one. `candidates` gets the words before the one being completed
+    pub fn complete(&mut self, candidates: impl FnOnce(&[&str]) -> Vec<String>) {
+        let (before, word) = match self.input.rfind(' ') {
+            Some(space) => self.input.split_at(space + 1),
+            None => ("", self.input.as_str()),
+        };
+        let words: Vec<&str> = before.split_whitespace().collect();
+        let matches: Vec<String> = candidates(&words)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+
+        let completed = match matches.as_slice() {
+            [] => return,
+            [only] => format!("{}{} ", before, only),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.as_str(), |common, candidate| {
+                    let length = common
+                        .char_indices()
+                        .zip(candidate.chars())
+                        .take_while(|((_, a), b)| a == b)
+                        .last()
+                        .map_or(0, |((idx, c), _)| idx + c.len_utf8());
+                    &common[..length]
+                });
+                let completed = format!("{}{}", before, common);
+                self.print(&matches.join("  "));
+                completed
+            }
+        };
+        self.input = completed;
+    }
+
+    /// Draw across the top of `renderer`, the input at the bottom with as much output above it as fits
+    pub fn draw<R: Renderer>(&self, renderer: &mut R) -> Result<(), String> {
+        let (width, height) = renderer.size();
+        let console_height = (height as f64 * HEIGHT) as u32;
+        renderer.fill_rect(Rect::new(0, 0, width, console_height), BACKGROUND)?;
+
+        // Measured, as the renderer's font may not be the bitmap one
+        let (_, text_height) = renderer.text_size("", TEXT_SCALE);
+        let (_, two_lines_height) = renderer.text_size("\n", TEXT_SCALE);
+        let line_height = (two_lines_height - text_height) as i32;
+        let left = PADDING as i32;
+        let mut y = console_height as i32 - PADDING as i32 - text_height as i32;
+        renderer.draw_text(
+            &format!("> {}_", self.input),
+            left,
+            y,
+            TEXT_SCALE,
+            INPUT_COLOR,
+        )?;
+
+        for line in self.output.iter().rev() {
+            y -= line_height;
+            if y < PADDING as i32 {
+                break;
+            }
+            renderer.draw_text(line, left, y, TEXT_SCALE, Color::WHITE)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entered(lines: &[&str]) -> Console {
+        let mut console = Console::default();
+        for line in lines {
+            console.type_text(line);
+            console.submit();
+        }
+        console
+    }
+
+    #[test]
+    fn history_stops_at_both_ends() {
+        let mut console = entered(&["first", "second"]);
+
+        // Going forward with nothing recalled leaves the input alone
+        console.type_text("typing");
+        console.history_forward();
+        assert_eq!(console.input, "typing");
+
+        console.history_back();
+        assert_eq!(console.input, "second");
+        console.history_back();
+        assert_eq!(console.input, "first");
+        console.history_back();
+        assert_eq!(console.input, "first");
+
+        console.history_forward();
+        assert_eq!(console.input, "second");
+        console.history_forward();
+        assert_eq!(console.input, "");
+        console.history_forward();
+        assert_eq!(console.input, "");
+    }
+
+    #[test]
+    fn history_skips_blank_and_repeated_lines() {
+        let mut console = entered(&["map", "map", " "]);
+        assert_eq!(console.history, ["map"]);
+
+        console.history_back();
+        console.history_back();
+        assert_eq!(console.input, "map");
+    }
+
+    #[test]
+    fn completes_a_single_match() {
+        let mut console = Console::default();
+        console.type_text("replay pl");
+        console.complete(|words| {
+            assert_eq!(words, ["replay"]);
+            vec!["record".to_owned(), "play".to_owned()]
+        });
+        assert_eq!(console.input, "replay play ");
+        assert!(console.output.is_empty());
+    }
+
+    #[test]
+    fn completes_several_matches_as_far_as_they_agree() {
+        let mut console = Console::default();
+        console.type_text("m");
+        console.complete(|_| {
+            vec![
+                "move_speed".to_owned(),
+                "mouse_sensitivity".to_owned(),
+                "noclip".to_owned(),
+            ]
+        });
+        assert_eq!(console.input, "mo");
+        assert_eq!(console.output, ["move_speed  mouse_sensitivity"]);
+
+        console.complete(|_| vec!["noclip".to_owned()]);
+        assert_eq!(console.input, "mo");
+    }
+
+    #[test]
+    fn output_is_capped() {
+        let mut console = Console::default();
+        for idx in 0..MAX_OUTPUT {
+            console.print(&format!("{}", idx));
+        }
+        console.print("last\nlines");
+
+        assert_eq!(console.output.len(), MAX_OUTPUT);
+        assert_eq!(console.output[0], "2");
+        assert_eq!(console.output[MAX_OUTPUT - 1], "lines");
+    }
+}
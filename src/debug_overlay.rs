@@ -0,0 +1,136 @@
+use std::{fmt::Write, time::Duration};
+
+use raycast::{
+    render::{Rect, Renderer},
+    Color,
+};
+
+use crate::state::State;
+
+const TEXT_SCALE: u32 = 2;
+/// Pixels between the text and the edges of its background
+const PADDING: u32 = 8;
+/// Pixels below the top of the screen, leaving the FPS counter room
+const TOP: i32 = 96;
+const BACKGROUND: Color = Color::rgba(0, 0, 0, 192);
+/// Weight a new frame's timings have in the averages shown
+const SMOOTHING: f64 = 0.05;
+
+/// How long each stage of one frame took
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameTimings {
+    pub events: Duration,
+    pub update: Duration,
+    pub cast: Duration,
+    pub render: Duration,
+}
+
+/// Camera, timing and map stats, plus an inspector listing everything the ray of the column under the mouse hit
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+    /// Running averages of each stage, in milliseconds, in the order of `FrameTimings`
+    averages: [f64; 4],
+    /// Mouse position in window pixels
+    mouse: Option<(i32, i32)>,
+}
+impl DebugOverlay {
+    pub fn record(&mut self, timings: FrameTimings) {
+        let stages = [timings.events, timings.update, timings.cast, timings.render];
+        for (average, stage) in self.averages.iter_mut().zip(stages.iter()) {
+            *average += (stage.as_secs_f64() * 1000. - *average) * SMOOTHING;
+        }
+    }
+
+    pub fn mouse_moved(&mut self, x: i32, y: i32) {
+        self.mouse = Some((x, y));
+    }
+
+    /// Column of the view under the mouse, in a `window_width` pixel wide window
+    pub fn hovered_column(&self, state: &State, window_width: u32) -> Option<usize> {
+        let (x, _) = self.mouse?;
+        let viewport = state.frame.viewport;
+        // The view can be rendered at a different resolution to the window
+        let x = (x as i64 * viewport.width as i64 / window_width.max(1) as i64) as i32;
+        viewport.column_at(x)
+    }
+
+    /// Draw the overlay over the whole of `renderer`, which is the size of the window
+    pub fn draw<R: Renderer>(&self, renderer: &mut R, state: &State) -> Result<(), String> {
+        let (width, _) = renderer.size();
+        let hovered = self.hovered_column(state, width);
+        let text = self.text(state, hovered);
+
+        let (text_width, text_height) = renderer.text_size(&text, TEXT_SCALE);
+        let x = width as i32 - (text_width + PADDING * 2) as i32;
+        renderer.fill_rect(
+            Rect::new(x, TOP, text_width + PADDING * 2, text_height + PADDING * 2),
+            BACKGROUND,
+        )?;
+        renderer.draw_text(
+            &text,
+            x + PADDING as i32,
+            TOP + PADDING as i32,
+            TEXT_SCALE,
+            Color::WHITE,
+        )?;
+
+        // Mark the inspected column across the screen
+        if let Some(idx) = hovered {
+            let (_, height) = renderer.size();
+            let viewport = state.frame.viewport;
+            let (column_x, column_width) = viewport.column_span(idx);
+            let to_window =
+                |x: i32| (x as i64 * width as i64 / viewport.width.max(1) as i64) as i32;
+            let left = to_window(column_x);
+            let right = to_window(column_x + column_width as i32).max(left + 1);
+            renderer.fill_rect(
+                Rect::new(left, 0, (right - left) as u32, height),
+                Color::rgba(255, 255, 0, 64),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn text(&self, state: &State, hovered: Option<usize>) -> String {
+        let camera = state.camera;
+        let frame = &state.frame;
+        let [events, update, cast, render] = self.averages;
+
+        // Writing to a `String` can't fail
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "POSITION {:.1}, {:.1}  ANGLE {:.1}",
+            camera.position.x,
+            camera.position.y,
+            camera.angle.to_degrees().rem_euclid(360.)
+        );
+        let _ = writeln!(
+            text,
+            "FOV {:.1}  COLUMNS {}  WALLS {}",
+            camera.fov,
+            frame.viewport.columns,
+            state.map.walls.len()
+        );
+        let _ = write!(
+            text,
+            "EVENTS {:.2}MS  UPDATE {:.2}MS  CAST {:.2}MS  RENDER {:.2}MS",
+            events, update, cast, render
+        );
+
+        if let Some((idx, column)) = hovered.and_then(|idx| Some((idx, frame.columns.get(idx)?))) {
+            let _ = write!(text, "\n\nCOLUMN {}, {} HITS", idx, column.hits.len());
+            for hit in column.hits.iter() {
+                let _ = write!(
+                    text,
+                    "\n#{}  RAW {:.1}  CORRECTED {:.1}  HEIGHT {}",
+                    hit.wall_index, hit.raw_distance, hit.distance, hit.height
+                );
+            }
+        }
+
+        text
+    }
+}
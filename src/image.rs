@@ -0,0 +1,81 @@
+//! Images in memory, loaded from and saved to PNG files
+
+use std::{fs::File, io::BufWriter, path::Path};
+
+use png::{BitDepth, ColorType, Transformations};
+
+use crate::color::Color;
+
+/// An RGBA image held in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Tightly packed RGBA rows
+    pub pixels: Vec<u8>,
+}
+impl Image {
+    /// A `width` by `height` image filled with `color`
+    pub fn new(width: u32, height: u32, color: Color) -> Self {
+        Self {
+            width,
+            height,
+            pixels: [color.r, color.g, color.b, color.a].repeat(width as usize * height as usize),
+        }
+    }
+
+    /// Read a PNG, converting whatever color type it uses to RGBA
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        // Palettes and low bit depths are expanded, 16 bit channels are cut to 8
+        decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let buf = &buf[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            ColorType::Rgba => buf.to_vec(),
+            ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            // Expanded away by the decoder
+            ColorType::Indexed => unreachable!("indexed PNGs are expanded when decoding"),
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    /// Write it out as an RGBA PNG
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), png::EncodingError> {
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)
+    }
+
+    /// Color of the pixel at `(x, y)`, `None` outside the image
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let idx = (y as usize * self.width as usize + x as usize) * 4;
+        let p = &self.pixels[idx..idx + 4];
+        Some(Color::rgba(p[0], p[1], p[2], p[3]))
+    }
+}
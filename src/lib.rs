@@ -0,0 +1,20 @@
+//! A 2D map ray caster drawn as a pseudo-3D view.
+//!
+//! Load a map with [`Map::load`], cast rays into it with [`caster::cast`] or a whole view at once with
+//! [`render::Frame::cast`], then draw the resulting columns. Drawing onto SDL canvases lives in
+//! `render::canvas` behind the default `sdl` feature, everything else works without SDL,
+//! including drawing into a [`render::Framebuffer`] in memory.
+
+#![warn(missing_docs)]
+
+pub mod caster;
+pub mod color;
+pub mod image;
+pub mod lighting;
+pub mod map;
+pub mod math;
+pub mod render;
+pub mod video;
+
+pub use color::Color;
+pub use map::Map;
@@ -0,0 +1,401 @@
+//! How walls are lit: fog with distance, ambient light and point lights, shadows included
+
+use crate::{
+    color::Color,
+    math::{segment::Segment, vector::Vec2, wall::Wall},
+};
+
+/// Spacing, in map units, between the samples of a baked lightmap
+const LIGHTMAP_TEXEL_SIZE: f64 = 8.;
+/// Small offset off a wall's face so occlusion tests don't hit the wall being lit
+const SURFACE_OFFSET: f64 = 0.01;
+
+/// How quickly surfaces fade into the fog as they get further away
+#[derive(Debug, Clone, Copy)]
+pub enum Attenuation {
+    /// Clear up to `start`, fully fogged from `end` on
+    Linear {
+        /// Distance fog starts at
+        start: f64,
+        /// Distance surfaces are fully fogged from
+        end: f64,
+    },
+    /// `1 - e^(-density * distance)`
+    Exponential {
+        /// How thick the fog is, higher fogging nearer surfaces
+        density: f64,
+    },
+}
+impl Attenuation {
+    /// How fogged a surface `distance` away is, 0 being clear and 1 being fully fogged
+    pub fn fog_amount(&self, distance: f64) -> f64 {
+        match *self {
+            Self::Linear { start, end } => {
+                if end <= start {
+                    return if distance < start { 0. } else { 1. };
+                }
+                ((distance - start) / (end - start)).clamp(0., 1.)
+            }
+            Self::Exponential { density } => 1. - (-density.max(0.) * distance).exp(),
+        }
+    }
+}
+
+/// Color surfaces fade to with distance
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    /// Color surfaces fade to
+    pub color: Color,
+    /// How quickly they fade with distance
+    pub attenuation: Attenuation,
+}
+
+/// How a map's walls, ceiling and floor are lit and fogged
+#[derive(Debug, Clone, Copy)]
+pub struct Lighting {
+    /// Brightness of a surface before fog, 0 to 1
+    pub ambient: f64,
+    /// How much darker surfaces facing along the x axis are, 0 to disable
+    pub side_shading: f64,
+    /// Fog surfaces fade into with distance
+    pub fog: Fog,
+}
+impl Lighting {
+    /// Shade `color` for a surface `distance` away, lit by `illumination` on top of the ambient level.
+    /// `x_facing` is how squarely the surface faces the x axis, 0 for floors and walls running along x, 1 for walls running along y
+    pub fn shade(
+        &self,
+        color: Color,
+        distance: f64,
+        x_facing: f64,
+        illumination: Illumination,
+    ) -> Color {
+        let side = 1. - self.side_shading * x_facing;
+        let light = (Illumination::white(self.ambient) + illumination) * side;
+
+        light
+            .apply(color)
+            .lerp(self.fog.color, self.fog.attenuation.fog_amount(distance))
+    }
+}
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            ambient: 1.,
+            side_shading: 0.,
+            fog: Fog {
+                color: Color::BLACK,
+                attenuation: Attenuation::Linear {
+                    start: 0.,
+                    end: 1000.,
+                },
+            },
+        }
+    }
+}
+
+/// Colored light falling on a surface, each channel 0 (dark) to 1 (fully lit) though it may exceed 1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Illumination {
+    /// Red light
+    pub r: f64,
+    /// Green light
+    pub g: f64,
+    /// Blue light
+    pub b: f64,
+}
+impl Illumination {
+    /// No light at all
+    pub const NONE: Self = Self {
+        r: 0.,
+        g: 0.,
+        b: 0.,
+    };
+
+    /// White light at `level`
+    pub fn white(level: f64) -> Self {
+        Self {
+            r: level,
+            g: level,
+            b: level,
+        }
+    }
+
+    /// Light the color of `color`, scaled by `level`
+    pub fn from_color(color: Color, level: f64) -> Self {
+        Self {
+            r: color.r as f64 / 255. * level,
+            g: color.g as f64 / 255. * level,
+            b: color.b as f64 / 255. * level,
+        }
+    }
+
+    /// Light `color` by this, keeping its alpha
+    pub fn apply(&self, color: Color) -> Color {
+        let channel = |c: u8, by: f64| (c as f64 * by).round().clamp(0., 255.) as u8;
+        Color::rgba(
+            channel(color.r, self.r),
+            channel(color.g, self.g),
+            channel(color.b, self.b),
+            color.a,
+        )
+    }
+}
+impl std::ops::Add for Illumination {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            r: self.r + rhs.r,
+            g: self.g + rhs.g,
+            b: self.b + rhs.b,
+        }
+    }
+}
+impl std::ops::AddAssign for Illumination {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl std::ops::Mul<f64> for Illumination {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self {
+            r: self.r * rhs,
+            g: self.g * rhs,
+            b: self.b * rhs,
+        }
+    }
+}
+
+/// A light radiating from a point in the map, lighting the walls it can see
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    /// Where the light is
+    pub position: Vec2,
+    /// Color of the light
+    pub color: Color,
+    /// Distance at which the light stops having any effect
+    pub radius: f64,
+    /// How bright the light is, before it falls off with distance and angle
+    pub intensity: f64,
+    /// How much the light's brightness wavers, 0 for a steady light and 1 for a light that flickers out completely
+    pub flicker: f64,
+    /// Static lights are baked into wall lightmaps when the map loads instead of being cast every frame
+    pub baked: bool,
+}
+impl PointLight {
+    /// Brightness multiplier at `time` seconds
+    pub fn flicker_at(&self, time: f64) -> f64 {
+        if self.flicker <= 0. {
+            return 1.;
+        }
+
+        // Two out of phase waves, offset per light so lights don't flicker in unison
+        let phase = self.position.x * 0.37 + self.position.y * 0.71;
+        let wave = ((time * 7.3 + phase).sin() * (time * 13.1 + phase * 1.7).sin()).abs();
+        1. - self.flicker.min(1.) * wave
+    }
+
+    /// Light falling on `point`, which lies on `walls[wall_index]`, from the side `eye` sees it from
+    pub fn illuminate(
+        &self,
+        point: Vec2,
+        wall_index: usize,
+        walls: &[Wall],
+        eye: Vec2,
+        time: f64,
+    ) -> Illumination {
+        self.illuminate_past(point, wall_index, walls, eye, time, &[])
+    }
+
+    /// `illuminate`, the walls at the indices in `ignored` letting light through
+    fn illuminate_past(
+        &self,
+        point: Vec2,
+        wall_index: usize,
+        walls: &[Wall],
+        eye: Vec2,
+        time: f64,
+        ignored: &[usize],
+    ) -> Illumination {
+        let wall = &walls[wall_index];
+        let to_light = self.position - point;
+        let distance = to_light.length();
+
+        if distance >= self.radius || distance == 0. {
+            return Illumination::NONE;
+        }
+
+        // Walls are two sided, only the face the eye is looking at is lit
+        let normal = wall.normal();
+        let facing = normal.dot(to_light);
+        if facing.signum() != normal.dot(eye - point).signum() {
+            return Illumination::NONE;
+        }
+
+        let origin = point + normal * (SURFACE_OFFSET * facing.signum());
+        // Light passes through see-through walls unhindered
+        let path = Segment::new(origin, self.position);
+        let occluded = walls.iter().enumerate().any(|(idx, other)| {
+            idx != wall_index
+                && !ignored.contains(&idx)
+                && !other.surface.is_see_through()
+                && path.intersect(&other.segment()).is_some()
+        });
+        if occluded {
+            return Illumination::NONE;
+        }
+
+        let falloff = (1. - distance / self.radius).powi(2);
+        let angle = facing.abs() / distance;
+
+        Illumination::from_color(
+            self.color,
+            self.intensity * falloff * angle * self.flicker_at(time),
+        )
+    }
+}
+
+/// Light from baked lights sampled along both faces of a wall
+#[derive(Debug, Clone)]
+pub struct Lightmap {
+    front: Vec<Illumination>,
+    back: Vec<Illumination>,
+}
+impl Lightmap {
+    /// Light at `point` on `wall` on the face seen from `eye`
+    pub fn sample(&self, wall: &Wall, point: Vec2, eye: Vec2) -> Illumination {
+        let samples = if wall.normal().dot(eye - point) >= 0. {
+            &self.front
+        } else {
+            &self.back
+        };
+
+        // Texels are spread evenly from end to end, so slightly closer together than LIGHTMAP_TEXEL_SIZE
+        let last = samples.len().saturating_sub(1);
+        let along = point.distance(wall.a) / wall.segment().direction().length().max(f64::EPSILON);
+        let idx = (along * last as f64).round() as usize;
+        samples.get(idx.min(last)).copied().unwrap_or_default()
+    }
+}
+
+/// Bake every `baked` light into lightmaps on the walls it reaches. The walls at the indices in `moving`, such as
+/// doors, won't stay where they are, so they're neither lit nor cast shadows
+pub fn bake_lightmaps(walls: &mut [Wall], lights: &[PointLight], moving: &[usize]) {
+    let baked: Vec<&PointLight> = lights.iter().filter(|light| light.baked).collect();
+    if baked.is_empty() {
+        return;
+    }
+
+    let lightmaps: Vec<Option<Lightmap>> = (0..walls.len())
+        .map(|wall_index| {
+            if moving.contains(&wall_index) {
+                return None;
+            }
+            let wall = &walls[wall_index];
+            let direction = wall.segment().direction();
+            let texels = (direction.length() / LIGHTMAP_TEXEL_SIZE).ceil() as usize + 1;
+            let normal = wall.normal();
+
+            let bake_side = |side: f64| -> Vec<Illumination> {
+                (0..texels)
+                    .map(|texel| {
+                        let along = (texel as f64 / (texels - 1).max(1) as f64).min(1.);
+                        let point = wall.a + direction * along;
+                        let eye = point + normal * side;
+                        baked.iter().fold(Illumination::NONE, |acc, light| {
+                            // Baked lights never flicker, so the time doesn't matter
+                            acc + light.illuminate_past(point, wall_index, walls, eye, 0., moving)
+                        })
+                    })
+                    .collect()
+            };
+
+            let lightmap = Lightmap {
+                front: bake_side(1.),
+                back: bake_side(-1.),
+            };
+            let lit = lightmap
+                .front
+                .iter()
+                .chain(lightmap.back.iter())
+                .any(|texel| *texel != Illumination::NONE);
+
+            if lit {
+                Some(lightmap)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    for (wall, lightmap) in walls.iter_mut().zip(lightmaps) {
+        wall.lightmap = lightmap;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_the_texel_nearest_a_baked_light() {
+        // 100 long, so its 14 texels are 100/13 apart rather than LIGHTMAP_TEXEL_SIZE
+        let mut walls = vec![Wall::new(
+            Vec2::new(0., 0.),
+            Vec2::new(100., 0.),
+            Color::WHITE,
+        )];
+        let light = PointLight {
+            position: Vec2::new(90., 10.),
+            color: Color::WHITE,
+            radius: 50.,
+            intensity: 1.,
+            flicker: 0.,
+            baked: true,
+        };
+        bake_lightmaps(&mut walls, &[light], &[]);
+
+        let wall = &walls[0];
+        let lightmap = wall.lightmap.as_ref().unwrap();
+        let below = Vec2::new(90., 0.);
+        let sampled = lightmap.sample(wall, below, light.position);
+        let brightest = lightmap
+            .front
+            .iter()
+            .chain(lightmap.back.iter())
+            .map(|texel| texel.r)
+            .fold(0., f64::max);
+        assert!(brightest > 0.);
+        assert_eq!(sampled.r, brightest);
+    }
+
+    #[test]
+    fn moving_walls_cast_no_baked_shadows() {
+        let wall = |y: f64| Wall::new(Vec2::new(0., y), Vec2::new(100., y), Color::WHITE);
+        // A door between the light and the wall behind it
+        let mut walls = vec![wall(0.), wall(5.)];
+        let light = PointLight {
+            position: Vec2::new(50., 10.),
+            color: Color::WHITE,
+            radius: 50.,
+            intensity: 1.,
+            flicker: 0.,
+            baked: true,
+        };
+
+        bake_lightmaps(&mut walls, &[light], &[]);
+        assert!(walls[0].lightmap.is_none());
+
+        bake_lightmaps(&mut walls, &[light], &[1]);
+        assert!(walls[1].lightmap.is_none());
+        let behind = &walls[0];
+        let sampled =
+            behind
+                .lightmap
+                .as_ref()
+                .unwrap()
+                .sample(behind, Vec2::new(50., 0.), light.position);
+        assert!(sampled.r > 0.);
+    }
+}
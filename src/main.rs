@@ -1,16 +1,55 @@
+#[cfg(feature = "sdl")]
 mod app;
-mod ext;
+mod bench;
+#[cfg(feature = "sdl")]
+mod console;
+#[cfg(feature = "sdl")]
+mod debug_overlay;
 mod key_state_handler;
-mod map;
-mod math;
+#[cfg(feature = "sdl")]
+mod menu;
+mod replay;
 mod state;
+#[cfg(feature = "terminal")]
+mod terminal;
+#[cfg(feature = "sdl")]
 use app::App;
 
-pub const WINDOW_WIDTH: u32 = 1920;
-pub const WINDOW_HEIGHT: u32 = 1080;
+/// Size of the window when it isn't fullscreen
+pub const WINDOW_WIDTH: u32 = 1280;
+pub const WINDOW_HEIGHT: u32 = 720;
 
 fn main() -> Result<(), String> {
-    App::new()?.start()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    Ok(())
+    // `bench` times rendering along a camera path without opening a window
+    if args.first().map(String::as_str) == Some("bench") {
+        return bench::run(&args[1..]);
+    }
+
+    // `--terminal` plays in the terminal instead of a window, in plain characters with `--ascii`
+    #[cfg(feature = "terminal")]
+    if args.iter().any(|arg| arg == "--terminal") {
+        let mode = if args.iter().any(|arg| arg == "--ascii") {
+            raycast::render::terminal::TerminalMode::Ascii
+        } else {
+            raycast::render::terminal::TerminalMode::HalfBlock
+        };
+        return terminal::run(mode);
+    }
+
+    #[cfg(feature = "sdl")]
+    {
+        let mut app = App::new()?;
+        // `--replay <path>` plays a recorded replay as soon as the game starts
+        if let Some(idx) = args.iter().position(|arg| arg == "--replay") {
+            let path = args.get(idx + 1).ok_or("--replay needs a path")?;
+            println!("{}", app.play_replay(path)?);
+        }
+        app.start()
+    }
+
+    // Without SDL there's no window to play in
+    #[cfg(not(feature = "sdl"))]
+    Err("Built without the sdl feature, play with --terminal or run bench instead".to_owned())
 }
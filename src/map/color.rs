@@ -0,0 +1,255 @@
+use std::{convert::TryFrom, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+
+#[derive(Debug)]
+pub enum ColorError {
+    UnknownName(String),
+    BadHex(String),
+    BadChannelCount(usize),
+}
+impl fmt::Display for ColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownName(name) => write!(f, "unknown color name \"{}\"", name),
+            Self::BadHex(hex) => write!(
+                f,
+                "invalid hex color \"{}\", expected #rgb, #rgba, #rrggbb or #rrggbbaa",
+                hex
+            ),
+            Self::BadChannelCount(count) => {
+                write!(f, "expected 3 or 4 color channels, found {}", count)
+            }
+        }
+    }
+}
+
+/// A color as written in a map file, any of:
+/// - a name, `"red"`
+/// - a hex string, `"#f80"`, `"#ff8800"` or with alpha `"#ff880080"`
+/// - channels, `[255, 136, 0]` or `[255, 136, 0, 128]`
+/// - an object, `{ "r": 255, "g": 136, "b": 0, "a": 128 }`, `a` defaulting to opaque
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "MapFileColorRepr", into = "MapFileColorRepr")]
+pub struct MapFileColor(pub Color);
+impl From<MapFileColor> for Color {
+    fn from(this: MapFileColor) -> Self {
+        this.0
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum MapFileColorRepr {
+    Text(String),
+    Channels(Vec<u8>),
+    Rgba {
+        r: u8,
+        g: u8,
+        b: u8,
+        #[serde(default = "opaque")]
+        a: u8,
+    },
+}
+
+fn opaque() -> u8 {
+    255
+}
+
+impl TryFrom<MapFileColorRepr> for MapFileColor {
+    type Error = ColorError;
+
+    fn try_from(repr: MapFileColorRepr) -> Result<Self, Self::Error> {
+        let color = match repr {
+            MapFileColorRepr::Text(text) if text.starts_with('#') => parse_hex(&text)?,
+            MapFileColorRepr::Text(name) => named(&name).ok_or(ColorError::UnknownName(name))?,
+            MapFileColorRepr::Channels(channels) => match channels[..] {
+                [r, g, b] => Color::rgb(r, g, b),
+                [r, g, b, a] => Color::rgba(r, g, b, a),
+                _ => return Err(ColorError::BadChannelCount(channels.len())),
+            },
+            MapFileColorRepr::Rgba { r, g, b, a } => Color::rgba(r, g, b, a),
+        };
+
+        Ok(Self(color))
+    }
+}
+
+impl From<MapFileColor> for MapFileColorRepr {
+    fn from(MapFileColor(color): MapFileColor) -> Self {
+        if color.a == 255 {
+            Self::Text(format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b))
+        } else {
+            Self::Text(format!(
+                "#{:02x}{:02x}{:02x}{:02x}",
+                color.r, color.g, color.b, color.a
+            ))
+        }
+    }
+}
+
+fn parse_hex(text: &str) -> Result<Color, ColorError> {
+    let digits = &text[1..];
+    let bad_hex = || ColorError::BadHex(text.to_string());
+
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad_hex());
+    }
+
+    // Short forms repeat each digit, #f80 == #ff8800
+    let channel = |idx: usize, width: usize| -> Result<u8, ColorError> {
+        let slice = &digits[idx * width..(idx + 1) * width];
+        let value = u8::from_str_radix(slice, 16).map_err(|_| bad_hex())?;
+        Ok(if width == 1 { value * 17 } else { value })
+    };
+
+    match digits.len() {
+        3 => Ok(Color::rgb(channel(0, 1)?, channel(1, 1)?, channel(2, 1)?)),
+        4 => Ok(Color::rgba(
+            channel(0, 1)?,
+            channel(1, 1)?,
+            channel(2, 1)?,
+            channel(3, 1)?,
+        )),
+        6 => Ok(Color::rgb(channel(0, 2)?, channel(1, 2)?, channel(2, 2)?)),
+        8 => Ok(Color::rgba(
+            channel(0, 2)?,
+            channel(1, 2)?,
+            channel(2, 2)?,
+            channel(3, 2)?,
+        )),
+        _ => Err(bad_hex()),
+    }
+}
+
+fn named(name: &str) -> Option<Color> {
+    let color = match name.to_ascii_lowercase().as_str() {
+        "black" => Color::BLACK,
+        "white" => Color::WHITE,
+        "red" => Color::RED,
+        "green" => Color::GREEN,
+        "blue" => Color::BLUE,
+        "yellow" => Color::YELLOW,
+        "cyan" => Color::CYAN,
+        "magenta" => Color::MAGENTA,
+        "gray" | "grey" => Color::GRAY,
+        "orange" => Color::rgb(255, 165, 0),
+        "purple" => Color::rgb(128, 0, 128),
+        "pink" => Color::rgb(255, 192, 203),
+        "brown" => Color::rgb(139, 69, 19),
+        "transparent" => Color::TRANSPARENT,
+        _ => return None,
+    };
+
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::map::{Map, MapWarning};
+
+    fn from_json(json: &str) -> Result<Color, serde_json::Error> {
+        serde_json::from_str::<MapFileColor>(json).map(Color::from)
+    }
+
+    #[test]
+    fn parses_every_hex_length() {
+        assert_eq!(parse_hex("#f80").unwrap(), Color::rgb(255, 136, 0));
+        assert_eq!(parse_hex("#f808").unwrap(), Color::rgba(255, 136, 0, 136));
+        assert_eq!(parse_hex("#FF8800").unwrap(), Color::rgb(255, 136, 0));
+        assert_eq!(
+            parse_hex("#ff880080").unwrap(),
+            Color::rgba(255, 136, 0, 128)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_hex() {
+        for text in [
+            "#",
+            "#ff",
+            "#ff880",
+            "#ff8800800",
+            "#gg8800",
+            "#ééé",
+            "#ff88 0",
+        ] {
+            assert!(
+                matches!(parse_hex(text), Err(ColorError::BadHex(_))),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn names_ignore_case() {
+        assert_eq!(named("Grey"), Some(Color::GRAY));
+        assert_eq!(named("ORANGE"), Some(Color::rgb(255, 165, 0)));
+        assert_eq!(named("chartreuse"), None);
+        assert!(from_json("\"chartreuse\"").is_err());
+    }
+
+    #[test]
+    fn reads_every_representation() {
+        assert_eq!(from_json("\"red\"").unwrap(), Color::RED);
+        assert_eq!(from_json("\"#0000ff\"").unwrap(), Color::BLUE);
+        assert_eq!(from_json("[1, 2, 3]").unwrap(), Color::rgb(1, 2, 3));
+        assert_eq!(from_json("[1, 2, 3, 4]").unwrap(), Color::rgba(1, 2, 3, 4));
+        assert!(from_json("[1, 2]").is_err());
+        assert!(from_json("[1, 2, 3, 4, 5]").is_err());
+        assert_eq!(
+            from_json(r#"{ "r": 1, "g": 2, "b": 3 }"#).unwrap(),
+            Color::rgba(1, 2, 3, 255)
+        );
+        assert_eq!(
+            from_json(r#"{ "r": 1, "g": 2, "b": 3, "a": 4 }"#).unwrap(),
+            Color::rgba(1, 2, 3, 4)
+        );
+    }
+
+    #[test]
+    fn writes_hex_that_reads_back() {
+        for color in [Color::rgb(255, 136, 0), Color::rgba(1, 2, 3, 4)] {
+            let json = serde_json::to_string(&MapFileColor(color)).unwrap();
+            assert_eq!(from_json(&json).unwrap(), color);
+        }
+    }
+
+    #[test]
+    fn warns_about_missing_palette_entries() {
+        let path =
+            std::env::temp_dir().join(format!("raycast-palette-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{
+                "dimensions": [100, 100],
+                "wall_colors": ["red"],
+                "walls": [
+                    { "wall_color": 0, "start": { "x": 0, "y": 0 }, "end": { "x": 100, "y": 0 } },
+                    { "wall_color": 3, "start": { "x": 0, "y": 0 }, "end": { "x": 0, "y": 100 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let map = Map::load(&path);
+        fs::remove_file(&path).unwrap();
+        let map = map.unwrap();
+
+        assert_eq!(map.walls[0].color, Color::RED);
+        assert_eq!(map.walls[1].color, Color::WHITE);
+        assert!(matches!(
+            map.warnings[..],
+            [MapWarning::MissingPaletteEntry {
+                wall: 1,
+                index: 3,
+                palette_len: 1
+            }]
+        ));
+    }
+}
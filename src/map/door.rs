@@ -0,0 +1,255 @@
+use crate::math::{segment::Segment, vector::Vec2, wall::Wall};
+
+/// How a door moves out of the way
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoorKind {
+    /// Slides along its own length, past its start point
+    Slide,
+    /// Swings around its start point by `angle` radians, positive is clockwise
+    Swing {
+        /// How far it swings open
+        angle: f64,
+    },
+}
+
+/// Where a door is in its open and close cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorState {
+    /// Shut, and staying that way until opened
+    Closed,
+    /// Moving toward open
+    Opening,
+    /// Fully open, closing by itself after `auto_close` seconds if set
+    Open,
+    /// Moving toward closed
+    Closing,
+}
+
+/// A wall segment that moves between a closed and open position.
+/// The door's current segment lives in `Map::walls` at `wall_index` so casting and collision see it like any other wall
+#[derive(Debug, Clone)]
+pub struct Door {
+    /// Index of the door's wall in `Map::walls`
+    pub wall_index: usize,
+    /// How it moves
+    pub kind: DoorKind,
+    /// Where it is in opening and closing
+    pub state: DoorState,
+    /// Fraction of the full motion covered per second
+    pub speed: f64,
+    /// Seconds a door stays open before closing by itself, `None` to stay open
+    pub auto_close: Option<f64>,
+    closed: Segment,
+    /// 0 when closed, 1 when fully open
+    progress: f64,
+    open_for: f64,
+}
+impl Door {
+    /// A closed door, closing over where `wall` is now
+    pub fn new(
+        wall: &Wall,
+        wall_index: usize,
+        kind: DoorKind,
+        speed: f64,
+        auto_close: Option<f64>,
+    ) -> Self {
+        Self {
+            wall_index,
+            kind,
+            state: DoorState::Closed,
+            speed,
+            auto_close,
+            closed: wall.segment(),
+            progress: 0.,
+            open_for: 0.,
+        }
+    }
+
+    /// Open the door if it's closed or closing, close it if it's open or opening
+    pub fn interact(&mut self) {
+        self.state = match self.state {
+            DoorState::Closed | DoorState::Closing => DoorState::Opening,
+            DoorState::Open | DoorState::Opening => DoorState::Closing,
+        };
+    }
+
+    /// Reopen a closing door, for when something is in its way
+    pub fn block(&mut self) {
+        if self.state == DoorState::Closing {
+            self.state = DoorState::Opening;
+        }
+    }
+
+    /// Advance the door by `dt` seconds
+    pub fn update(&mut self, dt: f64) {
+        let step = self.speed * dt;
+
+        match self.state {
+            DoorState::Closed => {}
+            DoorState::Opening => {
+                self.progress = (self.progress + step).min(1.);
+                if self.progress >= 1. {
+                    self.state = DoorState::Open;
+                    self.open_for = 0.;
+                }
+            }
+            DoorState::Open => {
+                self.open_for += dt;
+                if matches!(self.auto_close, Some(delay) if self.open_for >= delay) {
+                    self.state = DoorState::Closing;
+                }
+            }
+            DoorState::Closing => {
+                self.progress = (self.progress - step).max(0.);
+                if self.progress <= 0. {
+                    self.state = DoorState::Closed;
+                }
+            }
+        }
+    }
+
+    /// Advance the door by `dt` seconds, unless that would bring it within `clearance` of `point`, where the player
+    /// is. It stays where it was instead, and reopens if it was closing
+    pub fn update_clear_of(&mut self, dt: f64, point: Vec2, clearance: f64) {
+        let before = self.clone();
+        self.update(dt);
+        if self.segment().distance_to(point) < clearance {
+            *self = before;
+            self.block();
+        }
+    }
+
+    /// Shortest distance from `point` to the door's closed position
+    pub fn closed_distance_to(&self, point: Vec2) -> f64 {
+        self.closed.distance_to(point)
+    }
+
+    /// The door at its current position
+    pub fn segment(&self) -> Segment {
+        let Segment { a, b } = self.closed;
+        let along = self.closed.direction();
+
+        match self.kind {
+            DoorKind::Slide => {
+                let offset = along * -self.progress;
+                Segment::new(a + offset, b + offset)
+            }
+            DoorKind::Swing { angle } => Segment::new(a, a + along.rotate(angle * self.progress)),
+        }
+    }
+
+    /// Move the door's wall to the door's current position
+    pub fn apply(&self, walls: &mut [Wall]) {
+        let Segment { a, b } = self.segment();
+        let wall = &mut walls[self.wall_index];
+        wall.a = a;
+        wall.b = b;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::color::Color;
+
+    fn door(kind: DoorKind, auto_close: Option<f64>) -> Door {
+        let wall = Wall::new(Vec2::new(0., 0.), Vec2::new(10., 0.), Color::WHITE);
+        // Fully opens or closes in half a second
+        Door::new(&wall, 0, kind, 2., auto_close)
+    }
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn opens_then_closes_by_itself() {
+        let mut door = door(DoorKind::Slide, Some(1.));
+        assert_eq!(door.state, DoorState::Closed);
+
+        door.interact();
+        assert_eq!(door.state, DoorState::Opening);
+        door.update(0.25);
+        assert_eq!(door.state, DoorState::Opening);
+        door.update(0.25);
+        assert_eq!(door.state, DoorState::Open);
+
+        door.update(0.5);
+        assert_eq!(door.state, DoorState::Open);
+        door.update(0.5);
+        assert_eq!(door.state, DoorState::Closing);
+
+        door.update(0.5);
+        assert_eq!(door.state, DoorState::Closed);
+        assert_close(door.segment().a, Vec2::new(0., 0.));
+    }
+
+    #[test]
+    fn blocking_reopens_a_closing_door() {
+        let mut door = door(DoorKind::Slide, None);
+        door.block();
+        assert_eq!(door.state, DoorState::Closed);
+
+        door.interact();
+        door.update(0.5);
+        door.update(1.);
+        assert_eq!(
+            door.state,
+            DoorState::Open,
+            "without auto_close it stays open"
+        );
+
+        door.interact();
+        door.update(0.25);
+        assert_eq!(door.state, DoorState::Closing);
+        door.block();
+        assert_eq!(door.state, DoorState::Opening);
+        door.update(0.25);
+        assert_eq!(door.state, DoorState::Open);
+    }
+
+    #[test]
+    fn stops_short_of_the_player_either_way() {
+        // In the way of the door swinging open, but clear of it closed
+        let player = Vec2::new(5., 5.);
+        let mut swing = door(DoorKind::Swing { angle: FRAC_PI_2 }, Some(0.));
+        swing.interact();
+        for _ in 0..60 {
+            swing.update_clear_of(1. / 60., player, 2.);
+            assert!(swing.segment().distance_to(player) >= 2.);
+        }
+        assert_eq!(swing.state, DoorState::Opening);
+
+        // In the way of it closing, having opened fully
+        let player = Vec2::new(3., 1.);
+        let mut slide = door(DoorKind::Slide, Some(0.));
+        slide.interact();
+        slide.update(0.5);
+        slide.update(0.);
+        assert_eq!(slide.state, DoorState::Closing);
+        for _ in 0..60 {
+            slide.update_clear_of(1. / 60., player, 2.);
+            assert!(slide.segment().distance_to(player) >= 2.);
+        }
+        assert_ne!(slide.state, DoorState::Closed);
+    }
+
+    #[test]
+    fn fully_open_positions() {
+        let mut slide = door(DoorKind::Slide, None);
+        slide.interact();
+        slide.update(0.5);
+        let segment = slide.segment();
+        assert_close(segment.a, Vec2::new(-10., 0.));
+        assert_close(segment.b, Vec2::new(0., 0.));
+
+        let mut swing = door(DoorKind::Swing { angle: FRAC_PI_2 }, None);
+        swing.interact();
+        swing.update(0.5);
+        let segment = swing.segment();
+        assert_close(segment.a, Vec2::new(0., 0.));
+        assert_close(segment.b, Vec2::new(0., 10.));
+    }
+}
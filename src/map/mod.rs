@@ -0,0 +1,704 @@
+//! Maps, the walls, doors, lights and spawn point a game takes place in, and loading them from JSON files
+
+mod color;
+mod door;
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs::File, io::Read, path::Path};
+
+use crate::{
+    color::Color,
+    image::Image,
+    lighting::{bake_lightmaps, Attenuation, Fog, Illumination, Lighting, PointLight},
+    math::{
+        vector::Vec2,
+        wall::{Surface, Wall},
+    },
+};
+
+pub use color::MapFileColor;
+pub use door::{Door, DoorKind, DoorState};
+
+/// Why a map couldn't be loaded
+#[derive(Debug)]
+pub enum MapError {
+    /// The map file couldn't be read
+    Io(std::io::Error),
+    /// The map file isn't valid map JSON
+    Json(serde_json::Error),
+    /// The map's sky image couldn't be read
+    Sky(png::DecodingError),
+}
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "couldn't read map: {}", err),
+            Self::Json(err) => write!(f, "invalid map file: {}", err),
+            Self::Sky(err) => write!(f, "couldn't load sky: {}", err),
+        }
+    }
+}
+impl std::error::Error for MapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+            Self::Sky(err) => Some(err),
+        }
+    }
+}
+impl From<std::io::Error> for MapError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for MapError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<png::DecodingError> for MapError {
+    fn from(err: png::DecodingError) -> Self {
+        Self::Sky(err)
+    }
+}
+
+/// Problems in a map file that don't prevent it from loading
+#[derive(Debug)]
+pub enum MapWarning {
+    /// A wall's color refers to a palette entry that doesn't exist
+    MissingPaletteEntry {
+        /// Index of the wall
+        wall: usize,
+        /// Palette entry it refers to
+        index: usize,
+        /// How many entries the palette has
+        palette_len: usize,
+    },
+    /// A translucent wall's color has no transparency, so it looks opaque
+    OpaqueTranslucentWall {
+        /// Index of the wall
+        wall: usize,
+    },
+    /// A mirror's color has no transparency, so it reflects nothing
+    OpaqueMirror {
+        /// Index of the wall
+        wall: usize,
+    },
+    /// A portal leads to a wall that doesn't exist
+    InvalidPortalTarget {
+        /// Index of the portal
+        wall: usize,
+        /// Index of the wall it leads to
+        target: usize,
+    },
+}
+impl fmt::Display for MapWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPaletteEntry {
+                wall,
+                index,
+                palette_len,
+            } => write!(
+                f,
+                "wall {} uses palette entry {} but the palette only has {} entries, using white",
+                wall, index, palette_len
+            ),
+            Self::OpaqueTranslucentWall { wall } => write!(
+                f,
+                "wall {} is translucent but its color is fully opaque, give it an alpha below 255",
+                wall
+            ),
+            Self::OpaqueMirror { wall } => write!(
+                f,
+                "wall {} is a mirror but its color is fully opaque so nothing will be reflected, give it an alpha below 255",
+                wall
+            ),
+            Self::InvalidPortalTarget { wall, target } => write!(
+                f,
+                "wall {} is a portal to wall {} which doesn't exist, treating it as opaque",
+                wall, target
+            ),
+        }
+    }
+}
+
+/// A map file as written on disk, see `Map::load` for turning one into a playable map
+#[derive(Serialize, Deserialize)]
+pub struct MapFile {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+
+    dimensions: (u32, u32),
+
+    #[serde(default)]
+    spawn: Option<MapFileSpawn>,
+
+    #[serde(default = "MapFile::default_wall_colors", alias = "palette")]
+    wall_colors: Vec<MapFileColor>,
+
+    #[serde(default)]
+    lighting: MapFileLighting,
+
+    #[serde(default)]
+    background: MapFileBackground,
+
+    /// Path to a PNG panorama shown above the horizon instead of the ceiling color, relative to the map file
+    #[serde(default)]
+    sky: Option<String>,
+
+    walls: Vec<MapFileWall>,
+
+    #[serde(default)]
+    doors: Vec<MapFileDoor>,
+
+    #[serde(default)]
+    lights: Vec<MapFileLight>,
+}
+impl MapFile {
+    // The palette every map used before palettes could be set per map
+    fn default_wall_colors() -> Vec<MapFileColor> {
+        vec![
+            MapFileColor(Color::rgb(128, 255, 0)),
+            MapFileColor(Color::rgb(0, 128, 255)),
+            MapFileColor(Color::rgb(255, 0, 128)),
+            MapFileColor(Color::rgb(0, 255, 0)),
+            MapFileColor(Color::rgb(0, 0, 255)),
+            MapFileColor(Color::WHITE),
+        ]
+    }
+}
+
+/// A wall as written in a map file
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileWall {
+    wall_color: MapFileWallColor,
+
+    start: MapFilePoint,
+
+    end: MapFilePoint,
+
+    #[serde(default)]
+    surface: MapFileSurface,
+
+    /// Multiple of the standard wall height, walls shorter than 1 can be seen over
+    #[serde(default = "MapFileWall::default_height")]
+    height: f64,
+}
+impl MapFileWall {
+    fn default_height() -> f64 {
+        1.
+    }
+}
+
+/// `{ "type": "opaque" }`, `{ "type": "translucent" }` to blend using the wall color's alpha,
+/// `{ "type": "grate", "bar_width": 4, "spacing": 16 }`, `{ "type": "mirror" }`,
+/// or `{ "type": "portal", "target": 3 }` where `target` is the index of the wall to come out of
+#[derive(Serialize, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum MapFileSurface {
+    #[default]
+    Opaque,
+    Translucent,
+    Grate {
+        bar_width: f64,
+        spacing: f64,
+    },
+    Mirror,
+    Portal {
+        target: usize,
+    },
+}
+impl From<MapFileSurface> for Surface {
+    fn from(this: MapFileSurface) -> Self {
+        match this {
+            MapFileSurface::Opaque => Self::Opaque,
+            MapFileSurface::Translucent => Self::Translucent,
+            MapFileSurface::Grate { bar_width, spacing } => Self::Grate { bar_width, spacing },
+            MapFileSurface::Mirror => Self::Mirror,
+            MapFileSurface::Portal { target } => Self::Portal { target },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileDoor {
+    /// Where the door sits when closed
+    #[serde(flatten)]
+    wall: MapFileWall,
+
+    #[serde(default)]
+    kind: MapFileDoorKind,
+
+    /// Seconds the door takes to fully open or close
+    #[serde(default = "MapFileDoor::default_duration")]
+    duration: f64,
+
+    /// Seconds before an open door closes by itself, omit to stay open
+    #[serde(default)]
+    auto_close: Option<f64>,
+}
+impl MapFileDoor {
+    fn default_duration() -> f64 {
+        1.
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum MapFileDoorKind {
+    #[default]
+    Slide,
+    /// `angle` is in degrees, positive is clockwise
+    Swing {
+        #[serde(default = "MapFileDoorKind::default_swing_angle")]
+        angle: f64,
+    },
+}
+impl MapFileDoorKind {
+    fn default_swing_angle() -> f64 {
+        90.
+    }
+}
+impl From<MapFileDoorKind> for DoorKind {
+    fn from(this: MapFileDoorKind) -> Self {
+        match this {
+            MapFileDoorKind::Slide => Self::Slide,
+            MapFileDoorKind::Swing { angle } => Self::Swing {
+                angle: angle.to_radians(),
+            },
+        }
+    }
+}
+
+/// Either an index into the map's palette or a color of its own
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum MapFileWallColor {
+    Index(usize),
+    Color(MapFileColor),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFilePoint {
+    x: f64,
+    y: f64,
+}
+impl From<MapFilePoint> for Vec2 {
+    fn from(this: MapFilePoint) -> Self {
+        Vec2::new(this.x, this.y)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileSpawn {
+    position: MapFilePoint,
+
+    /// Facing in degrees, clockwise from the positive x axis
+    #[serde(default)]
+    angle: f64,
+}
+impl From<MapFileSpawn> for Spawn {
+    fn from(this: MapFileSpawn) -> Self {
+        Self {
+            position: this.position.into(),
+            angle: this.angle.to_radians(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileLighting {
+    /// Brightness of walls and floors before fog, 0 (pitch black) to 1 (fully lit)
+    #[serde(default = "MapFileLighting::default_ambient")]
+    ambient: f64,
+
+    /// How much darker walls facing along the x axis are, 0 to 1
+    #[serde(default)]
+    side_shading: f64,
+
+    #[serde(default)]
+    fog: MapFileFog,
+}
+impl MapFileLighting {
+    fn default_ambient() -> f64 {
+        Lighting::default().ambient
+    }
+}
+impl Default for MapFileLighting {
+    fn default() -> Self {
+        Self {
+            ambient: Self::default_ambient(),
+            side_shading: 0.,
+            fog: MapFileFog::default(),
+        }
+    }
+}
+impl From<MapFileLighting> for Lighting {
+    fn from(this: MapFileLighting) -> Self {
+        Self {
+            ambient: this.ambient.clamp(0., 1.),
+            side_shading: this.side_shading.clamp(0., 1.),
+            fog: this.fog.into(),
+        }
+    }
+}
+
+/// `{ "color": "black", "curve": "linear", "start": 0, "end": 1000 }`
+/// or `{ "color": "black", "curve": "exponential", "density": 0.002 }`
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileFog {
+    color: MapFileColor,
+
+    #[serde(flatten)]
+    attenuation: MapFileAttenuation,
+}
+impl Default for MapFileFog {
+    fn default() -> Self {
+        let fog = Lighting::default().fog;
+        Self {
+            color: MapFileColor(fog.color),
+            attenuation: match fog.attenuation {
+                Attenuation::Linear { start, end } => MapFileAttenuation::Linear { start, end },
+                Attenuation::Exponential { density } => MapFileAttenuation::Exponential { density },
+            },
+        }
+    }
+}
+impl From<MapFileFog> for Fog {
+    fn from(this: MapFileFog) -> Self {
+        Self {
+            color: this.color.into(),
+            attenuation: match this.attenuation {
+                MapFileAttenuation::Linear { start, end } => Attenuation::Linear { start, end },
+                MapFileAttenuation::Exponential { density } => Attenuation::Exponential { density },
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "curve", rename_all = "snake_case")]
+pub(crate) enum MapFileAttenuation {
+    Linear {
+        #[serde(default)]
+        start: f64,
+        end: f64,
+    },
+    Exponential {
+        density: f64,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileLight {
+    position: MapFilePoint,
+
+    #[serde(default = "MapFileLight::default_color")]
+    color: MapFileColor,
+
+    radius: f64,
+
+    #[serde(default = "MapFileLight::default_intensity")]
+    intensity: f64,
+
+    /// 0 for a steady light, up to 1 for a light that flickers out completely
+    #[serde(default)]
+    flicker: f64,
+
+    /// Bake the light into wall lightmaps at load time, ignored for flickering lights
+    #[serde(default, rename = "static")]
+    baked: bool,
+}
+impl MapFileLight {
+    fn default_color() -> MapFileColor {
+        MapFileColor(Color::WHITE)
+    }
+
+    fn default_intensity() -> f64 {
+        1.
+    }
+}
+impl From<MapFileLight> for PointLight {
+    fn from(this: MapFileLight) -> Self {
+        let flicker = this.flicker.clamp(0., 1.);
+        Self {
+            position: this.position.into(),
+            color: this.color.into(),
+            radius: this.radius.max(0.),
+            intensity: this.intensity.max(0.),
+            flicker,
+            baked: this.baked && flicker == 0.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MapFileBackground {
+    ceiling: MapFileColor,
+    floor: MapFileColor,
+}
+impl Default for MapFileBackground {
+    fn default() -> Self {
+        Self {
+            ceiling: MapFileColor(Color::BLACK),
+            floor: MapFileColor(Color::BLACK),
+        }
+    }
+}
+impl From<MapFileBackground> for Background {
+    fn from(this: MapFileBackground) -> Self {
+        Self {
+            ceiling: this.ceiling.into(),
+            floor: this.floor.into(),
+        }
+    }
+}
+
+/// Optional information about a map for showing to players
+#[derive(Debug, Clone, Default)]
+pub struct MapMeta {
+    /// Title of the map
+    pub name: Option<String>,
+    /// Who made it
+    pub author: Option<String>,
+    /// A line or two about it
+    pub description: Option<String>,
+}
+
+/// Where the player starts
+#[derive(Debug, Clone, Copy)]
+pub struct Spawn {
+    /// Where the player stands
+    pub position: Vec2,
+    /// Facing in radians, clockwise from the positive x axis
+    pub angle: f64,
+}
+impl Spawn {
+    // Where the player was dropped before maps could specify a spawn
+    fn fallback(dims: (u32, u32)) -> Self {
+        let (w, h) = dims;
+        Self {
+            position: Vec2::new(w as f64 / 2., 50. + h as f64 / 2.),
+            angle: std::f64::consts::PI,
+        }
+    }
+}
+
+/// Colors of the ceiling and floor, the ceiling hidden behind the sky if the map has one
+#[derive(Debug, Clone, Copy)]
+pub struct Background {
+    /// Color above the horizon
+    pub ceiling: Color,
+    /// Color below the horizon
+    pub floor: Color,
+}
+
+/// A loaded map, ready to cast rays against
+pub struct Map {
+    /// Name, author and description, if the map gives them
+    pub meta: MapMeta,
+    /// Every wall including doors, which come after the static walls
+    pub walls: Vec<Wall>,
+    /// Every door, each moving the wall at its `wall_index`
+    pub doors: Vec<Door>,
+    /// Width and height in map units
+    pub dims: (u32, u32),
+    /// Where the player starts
+    pub spawn: Spawn,
+    /// Ambient light, side shading and fog
+    pub lighting: Lighting,
+    /// Every point light, baked or not
+    pub lights: Vec<PointLight>,
+    /// Ceiling and floor colors
+    pub background: Background,
+    /// A cylindrical panorama wrapped around the whole map, the left and right edges meeting behind the player.
+    /// The image's full height covers the view above the horizon
+    pub sky: Option<Image>,
+    /// Problems found while loading, which were worked around
+    pub warnings: Vec<MapWarning>,
+}
+impl Map {
+    /// Read and validate the map file at `path`, baking static lights and loading its sky
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MapError> {
+        let mut file = File::open(&path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        let file_parsed: MapFile = serde_json::from_str(&contents)?;
+
+        let wall_colors: Vec<Color> = file_parsed
+            .wall_colors
+            .into_iter()
+            .map(Color::from)
+            .collect();
+
+        let mut warnings = vec![];
+        let mut to_wall = |idx: usize, map_wall: MapFileWall| {
+            let color = match map_wall.wall_color {
+                MapFileWallColor::Color(color) => color.into(),
+                MapFileWallColor::Index(index) => {
+                    wall_colors.get(index).copied().unwrap_or_else(|| {
+                        warnings.push(MapWarning::MissingPaletteEntry {
+                            wall: idx,
+                            index,
+                            palette_len: wall_colors.len(),
+                        });
+                        Color::WHITE
+                    })
+                }
+            };
+
+            let surface: Surface = map_wall.surface.into();
+            if color.a == 255 {
+                match surface {
+                    Surface::Translucent => {
+                        warnings.push(MapWarning::OpaqueTranslucentWall { wall: idx })
+                    }
+                    Surface::Mirror => warnings.push(MapWarning::OpaqueMirror { wall: idx }),
+                    _ => {}
+                }
+            }
+
+            let mut wall = Wall::new(map_wall.start.into(), map_wall.end.into(), color);
+            wall.surface = surface;
+            wall.height = map_wall.height.max(0.);
+            wall
+        };
+
+        let mut walls: Vec<Wall> = file_parsed
+            .walls
+            .into_iter()
+            .enumerate()
+            .map(|(idx, map_wall)| to_wall(idx, map_wall))
+            .collect();
+
+        // Doors are walls too, appended after the static ones
+        let doors: Vec<Door> = file_parsed
+            .doors
+            .into_iter()
+            .map(|map_door| {
+                let wall_index = walls.len();
+                walls.push(to_wall(wall_index, map_door.wall));
+                Door::new(
+                    &walls[wall_index],
+                    wall_index,
+                    map_door.kind.into(),
+                    1. / map_door.duration.max(f64::EPSILON),
+                    map_door.auto_close,
+                )
+            })
+            .collect();
+
+        let wall_count = walls.len();
+        for (idx, wall) in walls.iter_mut().enumerate() {
+            if let Surface::Portal { target } = wall.surface {
+                if target >= wall_count || target == idx {
+                    warnings.push(MapWarning::InvalidPortalTarget { wall: idx, target });
+                    wall.surface = Surface::Opaque;
+                }
+            }
+        }
+
+        let lights: Vec<PointLight> = file_parsed
+            .lights
+            .into_iter()
+            .map(PointLight::from)
+            .collect();
+        let door_walls: Vec<usize> = doors.iter().map(|door| door.wall_index).collect();
+        bake_lightmaps(&mut walls, &lights, &door_walls);
+
+        // Relative to the map file, so maps load whatever the working directory is
+        let dir = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        let sky = file_parsed
+            .sky
+            .map(|sky| Image::load_png(dir.join(sky)))
+            .transpose()?;
+
+        let dims = file_parsed.dimensions;
+        let spawn = file_parsed
+            .spawn
+            .map(Spawn::from)
+            .unwrap_or_else(|| Spawn::fallback(dims));
+
+        Ok(Self {
+            meta: MapMeta {
+                name: file_parsed.name,
+                author: file_parsed.author,
+                description: file_parsed.description,
+            },
+            walls,
+            doors,
+            dims,
+            spawn,
+            lighting: file_parsed.lighting.into(),
+            lights,
+            background: file_parsed.background.into(),
+            sky,
+            warnings,
+        })
+    }
+
+    /// Light from the map's point lights falling on `point`, which lies on wall `wall_index`, on the side seen from `eye`.
+    /// `time` in seconds drives flickering lights
+    pub fn illumination_at(
+        &self,
+        wall_index: usize,
+        point: Vec2,
+        eye: Vec2,
+        time: f64,
+    ) -> Illumination {
+        let walls = &self.walls;
+        let baked = walls[wall_index]
+            .lightmap
+            .as_ref()
+            .map(|lightmap| lightmap.sample(&walls[wall_index], point, eye))
+            .unwrap_or_default();
+
+        self.lights
+            .iter()
+            .filter(|light| !light.baked)
+            .fold(baked, |acc, light| {
+                acc + light.illuminate(point, wall_index, walls, eye, time)
+            })
+    }
+}
+impl Default for Map {
+    fn default() -> Self {
+        Self::load("./assets/maps/standard.json").unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn finds_the_sky_next_to_the_map() {
+        let dir = std::env::temp_dir().join(format!("raycast-sky-{}", std::process::id()));
+        fs::create_dir_all(dir.join("maps")).unwrap();
+        Image::new(4, 2, Color::BLUE)
+            .save_png(dir.join("sky.png"))
+            .unwrap();
+        fs::write(
+            dir.join("maps/sky.json"),
+            r#"{ "dimensions": [100, 100], "sky": "../sky.png", "walls": [] }"#,
+        )
+        .unwrap();
+
+        let map = Map::load(dir.join("maps/sky.json"));
+        fs::remove_dir_all(&dir).unwrap();
+        let sky = map.unwrap().sky.unwrap();
+        assert_eq!((sky.width, sky.height), (4, 2));
+        assert_eq!(sky.pixel(0, 0), Some(Color::BLUE));
+    }
+}
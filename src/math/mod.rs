@@ -1,2 +1,97 @@
+//! The geometry ray casting is built on, and helpers for mapping numbers between ranges
+
+use num_traits::{AsPrimitive, Float};
+
+pub mod ray;
+pub mod segment;
+pub mod spline;
 pub mod vector;
 pub mod wall;
+
+/// Tolerance for geometry tests, so rays and paths meeting a wall exactly at its end still count as hitting it
+pub const EPSILON: f64 = 1e-9;
+
+/// Whether `t` lies within `0..=1`, give or take `EPSILON`
+fn within_unit(t: f64) -> bool {
+    (-EPSILON..=1. + EPSILON).contains(&t)
+}
+
+/// Map `value` from the range `old_min..old_max` onto `new_min..new_max`, extrapolating outside them
+pub fn remap<
+    T: 'static + Float + Copy,
+    ON: AsPrimitive<T> + Copy,
+    OX: AsPrimitive<T> + Copy,
+    NN: AsPrimitive<T> + Copy,
+    NX: AsPrimitive<T> + Copy,
+>(
+    value: T,
+    old_min: ON,
+    old_max: OX,
+    new_min: NN,
+    new_max: NX,
+) -> T {
+    new_min.as_()
+        + (new_max.as_() - new_min.as_())
+            * ((value - old_min.as_()) / (old_max.as_() - old_min.as_()))
+}
+
+/// `remap` for ranges both starting at zero
+pub fn remap_minz<
+    T: 'static + Float + Copy + AsPrimitive<T>,
+    OX: AsPrimitive<T> + Copy,
+    NX: AsPrimitive<T> + Copy,
+>(
+    value: T,
+    old_max: OX,
+    new_max: NX,
+) -> T {
+    remap(value, T::zero(), old_max, T::zero(), new_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn range() -> impl Strategy<Value = (f64, f64)> {
+        (-1e4f64..1e4, 1f64..1e4).prop_map(|(min, size)| (min, min + size))
+    }
+
+    #[test]
+    fn remap_examples() {
+        assert_eq!(remap(5., 0, 10, 0, 100), 50.);
+        assert_eq!(remap(0., -1, 1, 10, 20), 15.);
+        assert_eq!(remap(2., 0, 1, 0, 10), 20.);
+        assert_eq!(remap_minz(3., 4, 8), 6.);
+    }
+
+    proptest! {
+        #[test]
+        fn remap_maps_the_ends(old in range(), new in range()) {
+            prop_assert!((remap(old.0, old.0, old.1, new.0, new.1) - new.0).abs() < 1e-6);
+            prop_assert!((remap(old.1, old.0, old.1, new.0, new.1) - new.1).abs() < 1e-6);
+        }
+
+        #[test]
+        fn remap_is_invertible(old in range(), new in range(), value in -1e4f64..1e4) {
+            let there = remap(value, old.0, old.1, new.0, new.1);
+            let back = remap(there, new.0, new.1, old.0, old.1);
+            prop_assert!((back - value).abs() < 1e-6);
+        }
+
+        #[test]
+        fn remap_keeps_order(old in range(), new in range(), a in -1e4f64..1e4, b in -1e4f64..1e4) {
+            let (low, high) = (a.min(b), a.max(b));
+            prop_assert!(
+                remap(low, old.0, old.1, new.0, new.1) <= remap(high, old.0, old.1, new.0, new.1)
+            );
+        }
+
+        #[test]
+        fn remap_minz_scales(value in -1e4f64..1e4, old_max in 1f64..1e4, new_max in 1f64..1e4) {
+            let expected = value * new_max / old_max;
+            prop_assert!((remap_minz(value, old_max, new_max) - expected).abs() < 1e-6);
+        }
+    }
+}
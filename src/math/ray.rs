@@ -0,0 +1,253 @@
+//! Rays, half lines the caster follows from the camera
+
+use super::{segment::Segment, vector::Vec2, within_unit, EPSILON};
+
+/// A half line starting at `origin` heading along `dir`, which is always unit length
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    /// Where the ray starts
+    pub origin: Vec2,
+    /// Unit vector the ray heads along
+    pub dir: Vec2,
+}
+impl Ray {
+    /// A ray from `origin` heading along `dir`, which needn't be unit length
+    pub fn new(origin: Vec2, dir: Vec2) -> Self {
+        Self {
+            origin,
+            dir: dir.normalize(),
+        }
+    }
+
+    /// A ray heading `angle` radians from the x axis
+    pub fn from_angle(origin: Vec2, angle: f64) -> Self {
+        Self {
+            origin,
+            dir: Vec2::from_angle(angle),
+        }
+    }
+
+    /// Angle the ray heads at in radians, from the x axis
+    pub fn angle(&self) -> f64 {
+        self.dir.angle()
+    }
+
+    /// The point `distance` along the ray
+    pub fn at(&self, distance: f64) -> Vec2 {
+        self.origin + self.dir * distance
+    }
+
+    /// How far along the ray it crosses `segment`.
+    /// The segment's endpoints count as part of it, so a ray through the corner where two walls meet hits both.
+    /// Segments running parallel to the ray and hits right at the ray's origin don't count
+    pub fn intersect(&self, segment: &Segment) -> Option<f64> {
+        let s = segment.direction();
+        let denominator = self.dir.cross(s);
+
+        if denominator.abs() <= EPSILON * s.length() {
+            return None;
+        }
+
+        let offset = segment.a - self.origin;
+        let distance = offset.cross(s) / denominator;
+        let u = offset.cross(self.dir) / denominator;
+
+        if distance > EPSILON && within_unit(u) {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn segment(ax: f64, ay: f64, bx: f64, by: f64) -> Segment {
+        Segment::new(Vec2::new(ax, ay), Vec2::new(bx, by))
+    }
+
+    #[test]
+    fn dir_is_normalized() {
+        let ray = Ray::new(Vec2::ZERO, Vec2::new(0., 5.));
+        assert_eq!(ray.dir, Vec2::new(0., 1.));
+        assert_eq!(ray.angle(), FRAC_PI_2);
+        assert_eq!(ray.at(3.), Vec2::new(0., 3.));
+    }
+
+    #[test]
+    fn hits_segment_in_front() {
+        let ray = Ray::new(Vec2::new(1., 2.), Vec2::new(1., 0.));
+        let wall = segment(10., -5., 10., 5.);
+
+        let distance = ray.intersect(&wall).unwrap();
+        assert!((distance - 9.).abs() < EPSILON);
+        assert!(ray.at(distance).distance(Vec2::new(10., 2.)) < EPSILON);
+    }
+
+    #[test]
+    fn distance_is_measured_from_origin() {
+        // A wall half a unit away is still hit and the distance doesn't depend on the wall's direction
+        let wall = segment(0.5, 1., 0.5, -1.);
+        let ray = Ray::from_angle(Vec2::ZERO, 0.);
+
+        assert!((ray.intersect(&wall).unwrap() - 0.5).abs() < EPSILON);
+        let reversed = Segment::new(wall.b, wall.a);
+        assert!((ray.intersect(&reversed).unwrap() - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn misses_segment_behind() {
+        let ray = Ray::from_angle(Vec2::ZERO, PI);
+        assert_eq!(ray.intersect(&segment(10., -5., 10., 5.)), None);
+    }
+
+    #[test]
+    fn misses_segment_to_the_side() {
+        let ray = Ray::from_angle(Vec2::ZERO, 0.);
+        assert_eq!(ray.intersect(&segment(10., 1., 10., 5.)), None);
+        assert_eq!(ray.intersect(&segment(10., -5., 10., -1.)), None);
+    }
+
+    #[test]
+    fn misses_parallel_segment() {
+        let ray = Ray::from_angle(Vec2::ZERO, 0.);
+        assert_eq!(ray.intersect(&segment(1., 1., 10., 1.)), None);
+        assert_eq!(ray.intersect(&segment(1., 0., 10., 0.)), None);
+    }
+
+    #[test]
+    fn hits_at_every_angle() {
+        // A ray from the center of a square hits exactly one side in every direction
+        let sides = [
+            segment(-10., -10., 10., -10.),
+            segment(10., -10., 10., 10.),
+            segment(10., 10., -10., 10.),
+            segment(-10., 10., -10., -10.),
+        ];
+
+        for step in 0..64 {
+            // Offset so no ray goes exactly through a corner
+            let angle = (step as f64 + 0.5) * PI / 32.;
+            let ray = Ray::from_angle(Vec2::ZERO, angle);
+            let hits: Vec<f64> = sides.iter().filter_map(|s| ray.intersect(s)).collect();
+
+            assert_eq!(hits.len(), 1, "angle {}", angle);
+            assert!(hits[0] >= 10. - EPSILON && hits[0] <= 10. * 2f64.sqrt() + EPSILON);
+        }
+    }
+
+    #[test]
+    fn hits_both_walls_at_a_shared_corner() {
+        let first = segment(0., 10., 10., 10.);
+        let second = segment(10., 10., 10., 0.);
+        let ray = Ray::from_angle(Vec2::ZERO, FRAC_PI_4);
+
+        let expected = 10. * 2f64.sqrt();
+        assert!((ray.intersect(&first).unwrap() - expected).abs() < 1e-6);
+        assert!((ray.intersect(&second).unwrap() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ignores_hits_at_its_origin() {
+        // A ray leaving a wall doesn't hit it again on the way out
+        let wall = segment(0., -5., 0., 5.);
+        assert_eq!(Ray::from_angle(Vec2::ZERO, 0.).intersect(&wall), None);
+    }
+
+    fn coordinate() -> impl Strategy<Value = f64> {
+        -1000f64..1000.
+    }
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (coordinate(), coordinate()).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn wall() -> impl Strategy<Value = Segment> {
+        (point(), point())
+            .prop_map(|(a, b)| Segment::new(a, b))
+            .prop_filter("walls have length", |s| s.length() > 1.)
+    }
+
+    /// Somewhere along a segment, with its exact endpoints picked often
+    fn fraction() -> impl Strategy<Value = f64> {
+        prop_oneof![Just(0.), Just(1.), 0f64..=1.]
+    }
+
+    /// Distance from `point` to the infinite line through `segment`
+    fn line_distance(segment: &Segment, point: Vec2) -> f64 {
+        segment
+            .direction()
+            .normalize()
+            .cross(point - segment.a)
+            .abs()
+    }
+
+    proptest! {
+        #[test]
+        fn aimed_rays_hit(origin in point(), wall in wall(), t in fraction()) {
+            prop_assume!(line_distance(&wall, origin) > 1.);
+
+            let target = wall.point_at(t);
+            let ray = Ray::new(origin, target - origin);
+            let distance = ray.intersect(&wall);
+
+            prop_assert!(distance.is_some(), "{:?} missed {:?} at {}", ray, wall, t);
+            prop_assert!((distance.unwrap() - origin.distance(target)).abs() < 1e-6);
+        }
+
+        #[test]
+        fn rays_aimed_away_miss(origin in point(), wall in wall(), t in fraction()) {
+            prop_assume!(line_distance(&wall, origin) > 1.);
+
+            let ray = Ray::new(origin, origin - wall.point_at(t));
+            prop_assert_eq!(ray.intersect(&wall), None);
+        }
+
+        #[test]
+        fn rays_past_the_end_miss(origin in point(), wall in wall(), past in 0.01f64..10.) {
+            prop_assume!(line_distance(&wall, origin) > 1.);
+
+            let ray = Ray::new(origin, wall.point_at(1. + past) - origin);
+            prop_assert_eq!(ray.intersect(&wall), None);
+            let ray = Ray::new(origin, wall.point_at(-past) - origin);
+            prop_assert_eq!(ray.intersect(&wall), None);
+        }
+
+        #[test]
+        fn hit_point_lies_on_the_wall(origin in point(), angle in 0f64..PI * 2., wall in wall()) {
+            let ray = Ray::from_angle(origin, angle);
+
+            if let Some(distance) = ray.intersect(&wall) {
+                prop_assert!(distance > 0.);
+                prop_assert!(wall.distance_to(ray.at(distance)) < 1e-6);
+            }
+        }
+
+        /// No ray from inside a closed polygon escapes through a corner
+        #[test]
+        fn closed_shapes_have_no_gaps(
+            center in point(),
+            radii in prop::collection::vec(10f64..500., 3..12),
+            corner in 0usize..12,
+        ) {
+            // A star shaped polygon around `center`, every corner visible from it
+            let corners: Vec<Vec2> = radii
+                .iter()
+                .enumerate()
+                .map(|(i, r)| center + Vec2::from_angle(i as f64 * PI * 2. / radii.len() as f64) * *r)
+                .collect();
+            let sides: Vec<Segment> = (0..corners.len())
+                .map(|i| Segment::new(corners[i], corners[(i + 1) % corners.len()]))
+                .collect();
+
+            let ray = Ray::new(center, corners[corner % corners.len()] - center);
+            prop_assert!(sides.iter().any(|side| ray.intersect(side).is_some()));
+        }
+    }
+}
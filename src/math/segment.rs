@@ -0,0 +1,241 @@
+//! Line segments, what walls and doors are made of
+
+use super::{vector::Vec2, within_unit, EPSILON};
+
+/// The straight line between two points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    /// One end
+    pub a: Vec2,
+    /// The other end
+    pub b: Vec2,
+}
+impl Segment {
+    /// The segment from `a` to `b`
+    pub const fn new(a: Vec2, b: Vec2) -> Self {
+        Self { a, b }
+    }
+
+    /// From `a` to `b`, as long as the segment
+    pub fn direction(&self) -> Vec2 {
+        self.b - self.a
+    }
+
+    /// Distance between its ends
+    pub fn length(&self) -> f64 {
+        self.direction().length()
+    }
+
+    /// `a` at `t` = 0, `b` at `t` = 1
+    pub fn point_at(&self, t: f64) -> Vec2 {
+        self.a.lerp(self.b, t)
+    }
+
+    /// Unit vector perpendicular to the segment, a quarter turn counter-clockwise from its direction
+    pub fn normal(&self) -> Vec2 {
+        self.direction().normalize().perp()
+    }
+
+    /// Nearest point on the segment to `point`
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let direction = self.direction();
+        let length_squared = direction.length_squared();
+
+        if length_squared == 0. {
+            return self.a;
+        }
+
+        let t = ((point - self.a).dot(direction) / length_squared).clamp(0., 1.);
+        self.point_at(t)
+    }
+
+    /// Shortest distance from `point` to anywhere on the segment
+    pub fn distance_to(&self, point: Vec2) -> f64 {
+        self.closest_point(point).distance(point)
+    }
+
+    /// Where the two segments cross or touch, endpoints included. Parallel segments never intersect
+    pub fn intersect(&self, other: &Self) -> Option<Vec2> {
+        let r = self.direction();
+        let s = other.direction();
+        let denominator = r.cross(s);
+
+        if denominator.abs() <= EPSILON * r.length() * s.length() {
+            return None;
+        }
+
+        let offset = other.a - self.a;
+        let t = offset.cross(s) / denominator;
+        let u = offset.cross(r) / denominator;
+
+        if within_unit(t) && within_unit(u) {
+            Some(self.point_at(t))
+        } else {
+            None
+        }
+    }
+}
+impl From<(Vec2, Vec2)> for Segment {
+    fn from((a, b): (Vec2, Vec2)) -> Self {
+        Self::new(a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn segment(ax: f64, ay: f64, bx: f64, by: f64) -> Segment {
+        Segment::new(Vec2::new(ax, ay), Vec2::new(bx, by))
+    }
+
+    #[test]
+    fn direction_length_and_points() {
+        let s = segment(1., 1., 4., 5.);
+
+        assert_eq!(s.direction(), Vec2::new(3., 4.));
+        assert_eq!(s.length(), 5.);
+        assert_eq!(s.point_at(0.), s.a);
+        assert_eq!(s.point_at(1.), s.b);
+        assert_eq!(s.point_at(0.5), Vec2::new(2.5, 3.));
+    }
+
+    #[test]
+    fn normal_is_unit_and_perpendicular() {
+        let s = segment(0., 0., 10., 0.);
+        assert_eq!(s.normal(), Vec2::new(0., 1.));
+
+        let s = segment(2., -1., 5., 7.);
+        assert!((s.normal().length() - 1.).abs() < EPSILON);
+        assert!(s.normal().dot(s.direction()).abs() < EPSILON);
+    }
+
+    #[test]
+    fn distance_to_points() {
+        let s = segment(0., 0., 10., 0.);
+
+        // Beside the segment
+        assert_eq!(s.distance_to(Vec2::new(5., 3.)), 3.);
+        assert_eq!(s.distance_to(Vec2::new(5., -3.)), 3.);
+        // Past either end
+        assert_eq!(s.distance_to(Vec2::new(-3., 4.)), 5.);
+        assert_eq!(s.distance_to(Vec2::new(13., -4.)), 5.);
+        // On it
+        assert_eq!(s.distance_to(Vec2::new(7., 0.)), 0.);
+    }
+
+    #[test]
+    fn distance_to_degenerate_segment() {
+        let s = segment(1., 1., 1., 1.);
+        assert_eq!(s.distance_to(Vec2::new(4., 5.)), 5.);
+    }
+
+    #[test]
+    fn crossing_segments_intersect() {
+        let s = segment(0., 0., 10., 10.);
+        let other = segment(0., 10., 10., 0.);
+
+        let point = s.intersect(&other).unwrap();
+        assert!(point.distance(Vec2::new(5., 5.)) < EPSILON);
+        let point = other.intersect(&s).unwrap();
+        assert!(point.distance(Vec2::new(5., 5.)) < EPSILON);
+    }
+
+    #[test]
+    fn separate_segments_do_not_intersect() {
+        let s = segment(0., 0., 10., 0.);
+
+        // Would cross if s were longer
+        assert_eq!(s.intersect(&segment(15., -5., 15., 5.)), None);
+        // Would cross if the other were longer
+        assert_eq!(s.intersect(&segment(5., 1., 5., 5.)), None);
+        // Parallel
+        assert_eq!(s.intersect(&segment(0., 1., 10., 1.)), None);
+        // Collinear and overlapping
+        assert_eq!(s.intersect(&segment(5., 0., 15., 0.)), None);
+    }
+
+    #[test]
+    fn touching_segments_intersect() {
+        let s = segment(0., 0., 10., 0.);
+
+        // End to end
+        assert_eq!(
+            s.intersect(&segment(10., 0., 10., 10.)),
+            Some(Vec2::new(10., 0.))
+        );
+        // One end on the other segment
+        assert_eq!(
+            s.intersect(&segment(5., 0., 5., 10.)),
+            Some(Vec2::new(5., 0.))
+        );
+    }
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (-1000f64..1000., -1000f64..1000.).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn long_segment() -> impl Strategy<Value = Segment> {
+        (point(), point())
+            .prop_map(|(a, b)| Segment::new(a, b))
+            .prop_filter("segments have length", |s| s.length() > 1.)
+    }
+
+    proptest! {
+        #[test]
+        fn closest_point_is_closest(s in long_segment(), p in point(), t in 0f64..=1.) {
+            let distance = s.distance_to(p);
+
+            prop_assert!(distance >= 0.);
+            prop_assert!(distance <= p.distance(s.a) + EPSILON);
+            prop_assert!(distance <= p.distance(s.b) + EPSILON);
+            prop_assert!(distance <= p.distance(s.point_at(t)) + 1e-6);
+            prop_assert!(s.distance_to(s.closest_point(p)) < 1e-6);
+        }
+
+        #[test]
+        fn points_on_the_segment_have_no_distance(s in long_segment(), t in 0f64..=1.) {
+            prop_assert!(s.distance_to(s.point_at(t)) < 1e-6);
+        }
+
+        #[test]
+        fn normal_is_unit_and_perpendicular_to_any_segment(s in long_segment()) {
+            prop_assert!((s.normal().length() - 1.).abs() < EPSILON);
+            prop_assert!(s.normal().dot(s.direction().normalize()).abs() < EPSILON);
+        }
+
+        #[test]
+        fn crossing_through_a_point_intersects_there(
+            s in long_segment(),
+            t in prop_oneof![Just(0.), Just(1.), 0f64..=1.],
+            angle in 0.1f64..3.,
+            before in 1f64..100.,
+            after in 1f64..100.,
+        ) {
+            // A segment through `s` at `t`, turned `angle` radians from it
+            let point = s.point_at(t);
+            let across = s.direction().normalize().rotate(angle);
+            let other = Segment::new(point - across * before, point + across * after);
+
+            let hit = s.intersect(&other);
+            prop_assert!(hit.is_some(), "{:?} missed {:?}", other, s);
+            prop_assert!(hit.unwrap().distance(point) < 1e-6);
+            prop_assert!(other.intersect(&s).is_some());
+        }
+
+        #[test]
+        fn separated_segments_miss(s in long_segment(), offset in 0.01f64..100., other in long_segment()) {
+            // `other` squashed onto one side of `s`, clear of its line
+            let normal = s.normal();
+            let side = |p: Vec2| {
+                let along = p - s.a;
+                s.a + along - normal * along.dot(normal) + normal * (offset + along.dot(normal).abs())
+            };
+            let other = Segment::new(side(other.a), side(other.b));
+
+            prop_assert_eq!(s.intersect(&other), None);
+        }
+    }
+}
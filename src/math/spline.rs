@@ -0,0 +1,76 @@
+//! Smooth curves through points, for cameras to follow
+
+use super::vector::Vec2;
+
+/// A smooth closed loop through a list of points, as a Catmull-Rom spline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline {
+    points: Vec<Vec2>,
+}
+impl Spline {
+    /// A loop through `points` in order and back to the first. Needs at least one point
+    pub fn closed(points: Vec<Vec2>) -> Self {
+        assert!(!points.is_empty(), "a spline needs at least one point");
+        Self { points }
+    }
+
+    /// The point `t` of the way around the loop, wrapping outside `0..1`. Each span between two points takes
+    /// the same share of `t`, however long it is
+    pub fn point(&self, t: f64) -> Vec2 {
+        let ([p0, p1, p2, p3], t) = self.span(t);
+        let (t2, t3) = (t * t, t * t * t);
+        (p1 * 2.
+            + (p2 - p0) * t
+            + (p0 * 2. - p1 * 5. + p2 * 4. - p3) * t2
+            + (p3 - p0 + (p1 - p2) * 3.) * t3)
+            * 0.5
+    }
+
+    /// Direction the loop heads in at `t`, not normalized
+    pub fn tangent(&self, t: f64) -> Vec2 {
+        let ([p0, p1, p2, p3], t) = self.span(t);
+        ((p2 - p0)
+            + (p0 * 2. - p1 * 5. + p2 * 4. - p3) * (2. * t)
+            + (p3 - p0 + (p1 - p2) * 3.) * (3. * t * t))
+            * 0.5
+    }
+
+    /// The four points shaping the span `t` falls in, and how far along that span it is
+    fn span(&self, t: f64) -> ([Vec2; 4], f64) {
+        let count = self.points.len();
+        let along = t.rem_euclid(1.) * count as f64;
+        let idx = (along.floor() as usize).min(count - 1);
+        let point = |offset: usize| self.points[(idx + count + offset - 1) % count];
+        ([point(0), point(1), point(2), point(3)], along - idx as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn passes_through_each_point_and_loops() {
+        let points = vec![
+            Vec2::new(0., 0.),
+            Vec2::new(10., 0.),
+            Vec2::new(10., 10.),
+            Vec2::new(0., 10.),
+        ];
+        let spline = Spline::closed(points.clone());
+
+        for (idx, &point) in points.iter().enumerate() {
+            assert_close(spline.point(idx as f64 / 4.), point);
+        }
+        assert_close(spline.point(1.), points[0]);
+        assert_close(spline.point(-0.25), points[3]);
+
+        // Heading from the first point towards the second, curving round from the last
+        let tangent = spline.tangent(0.);
+        assert!(tangent.x > 0. && tangent.y < 0., "{:?}", tangent);
+    }
+}
@@ -1,152 +1,105 @@
-use num_traits::AsPrimitive;
-use sdl2::rect::Point;
+//! 2D vectors, for points and directions on a map
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
-use crate::state::{remap, remap_minz};
+use num_traits::AsPrimitive;
 
-use super::wall::Wall;
+use super::remap_minz;
 
-#[derive(Debug, Clone, Copy)]
-pub struct Vec2D {
-    x: f64,
-    y: f64,
-    pub angle: f64,
-    pub magnitude: f64,
+/// A point or direction in map space
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec2 {
+    /// Horizontal component
+    pub x: f64,
+    /// Vertical component
+    pub y: f64,
 }
-impl Vec2D {
-    pub const Origin: Self = Self {
-        x: 0.,
-        y: 0.,
-        angle: 0.,
-        magnitude: 0.,
-    };
-
-    pub fn new(x: f64, y: f64) -> Self {
-        Self {
-            x,
-            y,
-            angle: y.atan2(x),
-            magnitude: x.hypot(y),
-        }
-    }
+impl Vec2 {
+    /// The origin, or no movement at all
+    pub const ZERO: Self = Self { x: 0., y: 0. };
 
-    pub fn from_angle(angle: f64) -> Self {
-        Self::new(angle.cos(), angle.sin()).normalize()
+    /// A vector with components `x` and `y`
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
     }
 
-    pub fn apply<F: FnOnce((f64, f64)) -> (f64, f64)>(&self, f: F) -> Self {
-        let (x, y) = f(self.x_y());
-        Self::new(x, y)
+    /// Unit vector pointing `angle` radians from the x axis
+    pub fn from_angle(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin)
     }
 
-    pub fn normalize(self) -> Self {
-        Self::new(self.x / self.magnitude, self.y / self.magnitude)
+    /// Euclidean length
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
     }
 
-    pub fn dist(&self, other: &Self) -> f64 {
-        (*self + -*other).magnitude
+    /// Length squared, cheaper than `length` when only comparing lengths
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
     }
 
-    pub fn is_origin(&self) -> bool {
-        self.x == 0. && self.y == 0.
+    /// Angle from the x axis in radians, in `-PI..=PI`
+    pub fn angle(self) -> f64 {
+        self.y.atan2(self.x)
     }
 
-    pub fn intersects(&self, wall: &Wall) -> Option<Vec2D> {
-        let (x1, y1) = wall.a.x_y();
-        let (x2, y2) = wall.b.x_y();
-        let (x3, y3) = self.x_y();
-        let x4: f64 = self.x() + self.angle.cos();
-        let y4: f64 = self.y() + self.angle.sin();
-
-        let den = (x1 - x2) * (y3 - y4) - (y1 - y2) * (x3 - x4);
-
-        if den == 0.0 {
-            return None;
-        }
-
-        let num1 = (x1 - x3) * (y3 - y4) - (y1 - y3) * (x3 - x4);
-        let num2 = -1.0 * ((x1 - x2) * (y1 - y3) - (y1 - y2) * (x1 - x3));
-
-        let t = num1 / den;
-        let u = num2 / den;
-
-        if t > 0.0 && t < 1.0 && u > 0.0 {
-            Some(Self::new(x1 + t * (x2 - x1), y1 + t * (y2 - y1)))
+    /// Same direction with a length of 1, the zero vector stays zero
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == 0. {
+            self
         } else {
-            None
-        }
-    }
-
-    pub fn clamp(&mut self, dims: (u32, u32), padding: f64) {
-        if self.x < padding {
-            self.set_x(padding)
-        }
-
-        if self.x > dims.0 as f64 - padding {
-            self.set_x(dims.0 as f64 - padding)
-        }
-
-        if self.y < padding {
-            self.set_y(padding)
-        }
-
-        if self.y > dims.1 as f64 - padding {
-            self.set_y(dims.1 as f64 - padding)
+            self / length
         }
     }
 
-    pub fn x_y(&self) -> (f64, f64) {
-        (self.x, self.y)
-    }
-
-    pub fn x(&self) -> f64 {
-        self.x
-    }
-
-    pub fn y(&self) -> f64 {
-        self.y
+    /// Whether both components are exactly zero
+    pub fn is_zero(self) -> bool {
+        self == Self::ZERO
     }
 
-    pub fn set_x(&mut self, x: f64) {
-        self.x = x;
-        self.angle = self.y.atan2(x);
+    /// Euclidean distance to `other`
+    pub fn distance(self, other: Self) -> f64 {
+        (self - other).length()
     }
 
-    pub fn set_y(&mut self, y: f64) {
-        self.y = y;
-        self.angle = self.x.atan2(y);
+    /// Dot product with `other`
+    pub fn dot(self, other: Self) -> f64 {
+        self.x * other.x + self.y * other.y
     }
 
-    pub fn set_x_raw(&mut self, x: f64) {
-        self.x = x;
+    /// z component of the 3D cross product, positive when `other` is counter-clockwise from `self`
+    pub fn cross(self, other: Self) -> f64 {
+        self.x * other.y - self.y * other.x
     }
 
-    pub fn set_y_raw(&mut self, y: f64) {
-        self.y = y;
+    /// Rotated a quarter turn counter-clockwise
+    pub fn perp(self) -> Self {
+        Self::new(-self.y, self.x)
     }
 
-    pub fn set_angle(&mut self, angle: f64) {
-        self.angle = angle;
-        self.x += self.angle.cos();
-        self.y += self.angle.sin();
-    }
-    pub fn set_angle_raw(&mut self, angle: f64) {
-        self.angle = angle;
+    /// Rotated `angle` radians counter-clockwise
+    pub fn rotate(self, angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
     }
 
-    pub fn add_x_y_raw(&mut self, x_y: (f64, f64)) {
-        let (x, y) = x_y;
-        self.x += x;
-        self.y += y;
+    /// `self` at `t` = 0, `other` at `t` = 1
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
     }
 
-    pub fn translate(&mut self, other: &Self) -> Self {
-        self.x += other.x();
-        self.y += other.y();
-
-        *self
+    /// Kept `padding` away from the edges of a `dims` sized area
+    pub fn clamp(self, dims: (u32, u32), padding: f64) -> Self {
+        Self::new(
+            self.x.max(padding).min(dims.0 as f64 - padding),
+            self.y.max(padding).min(dims.1 as f64 - padding),
+        )
     }
 
-    pub fn remap<OD: Into<Self>, ND: Into<Self>>(&self, old_dims: OD, new_dims: ND) -> Self {
+    /// Scale from a space `old_dims` in size to one `new_dims` in size, such as map units to minimap pixels
+    pub fn remap<OD: Into<Self>, ND: Into<Self>>(self, old_dims: OD, new_dims: ND) -> Self {
         let old_vector: Self = old_dims.into();
         let new_vector: Self = new_dims.into();
         Self::new(
@@ -155,41 +108,267 @@ impl Vec2D {
         )
     }
 }
-impl From<Vec2D> for Point {
-    fn from(this: Vec2D) -> Self {
-        Point::new(this.x.round() as i32, this.y.round() as i32)
+#[cfg(feature = "sdl")]
+impl From<Vec2> for sdl2::rect::Point {
+    fn from(this: Vec2) -> Self {
+        Self::new(this.x.round() as i32, this.y.round() as i32)
     }
 }
-impl<F: AsPrimitive<f64>> From<(F, F)> for Vec2D {
+impl<F: AsPrimitive<f64>> From<(F, F)> for Vec2 {
     fn from((x, y): (F, F)) -> Self {
         Self::new(x.as_(), y.as_())
     }
 }
-impl std::ops::Mul<f64> for Vec2D {
-    type Output = Vec2D;
+impl Add for Vec2 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+impl Sub for Vec2 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+impl Mul<f64> for Vec2 {
+    type Output = Self;
     fn mul(self, rhs: f64) -> Self::Output {
         Self::new(self.x * rhs, self.y * rhs)
     }
 }
-impl std::ops::MulAssign<f64> for Vec2D {
+impl MulAssign<f64> for Vec2 {
     fn mul_assign(&mut self, rhs: f64) {
         *self = *self * rhs;
     }
 }
-impl std::ops::Add for Vec2D {
-    type Output = Vec2D;
-    fn add(self, rhs: Self) -> Self::Output {
-        Self::new(self.x + rhs.x, self.y + rhs.y)
+impl Div<f64> for Vec2 {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self::Output {
+        Self::new(self.x / rhs, self.y / rhs)
     }
 }
-impl std::ops::AddAssign for Vec2D {
-    fn add_assign(&mut self, rhs: Self) {
-        *self = *self + rhs;
+impl DivAssign<f64> for Vec2 {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
     }
 }
-impl std::ops::Neg for Vec2D {
+impl Neg for Vec2 {
     type Output = Self;
     fn neg(self) -> Self::Output {
-        self * -1.
+        Self::new(-self.x, -self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::math::EPSILON;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn length_and_angle_follow_components() {
+        let mut v = Vec2::new(3., 4.);
+        assert_eq!(v.length(), 5.);
+        assert_eq!(v.length_squared(), 25.);
+
+        v.x = 0.;
+        assert_eq!(v.length(), 4.);
+        assert_eq!(v.angle(), FRAC_PI_2);
+
+        v.y = -2.;
+        assert_eq!(v.angle(), -FRAC_PI_2);
+    }
+
+    #[test]
+    fn from_angle_is_unit() {
+        for step in 0..16 {
+            let angle = step as f64 * PI / 8.;
+            let v = Vec2::from_angle(angle);
+            assert!((v.length() - 1.).abs() < EPSILON);
+            assert!((v.angle() - angle).sin().abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        assert_close(Vec2::new(0., -7.).normalize(), Vec2::new(0., -1.));
+        assert_close(Vec2::new(3., 4.).normalize(), Vec2::new(0.6, 0.8));
+        assert_eq!(Vec2::ZERO.normalize(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Vec2::new(1., 2.);
+        let b = Vec2::new(-3., 5.);
+
+        assert_eq!(a + b, Vec2::new(-2., 7.));
+        assert_eq!(a - b, Vec2::new(4., -3.));
+        assert_eq!(a * 2., Vec2::new(2., 4.));
+        assert_eq!(b / 2., Vec2::new(-1.5, 2.5));
+        assert_eq!(-a, Vec2::new(-1., -2.));
+
+        let mut c = a;
+        c += b;
+        c -= a;
+        assert_eq!(c, b);
+        c *= 4.;
+        c /= 2.;
+        assert_eq!(c, b * 2.);
+    }
+
+    #[test]
+    fn dot_and_cross() {
+        let x = Vec2::new(1., 0.);
+        let y = Vec2::new(0., 1.);
+
+        assert_eq!(x.dot(y), 0.);
+        assert_eq!(x.dot(x), 1.);
+        assert_eq!(Vec2::new(2., 3.).dot(Vec2::new(4., -1.)), 5.);
+
+        assert_eq!(x.cross(y), 1.);
+        assert_eq!(y.cross(x), -1.);
+        assert_eq!(x.cross(x * 3.), 0.);
+    }
+
+    #[test]
+    fn perp_and_rotate() {
+        let v = Vec2::new(2., 1.);
+
+        assert_eq!(v.perp(), Vec2::new(-1., 2.));
+        assert_eq!(v.dot(v.perp()), 0.);
+        assert_close(v.rotate(FRAC_PI_2), v.perp());
+        assert_close(v.rotate(PI), -v);
+        assert_close(v.rotate(0.3).rotate(-0.3), v);
+        assert!((v.rotate(1.1).length() - v.length()).abs() < EPSILON);
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Vec2::new(0., 10.);
+        let b = Vec2::new(10., 0.);
+
+        assert_eq!(a.lerp(b, 0.), a);
+        assert_eq!(a.lerp(b, 1.), b);
+        assert_eq!(a.lerp(b, 0.5), Vec2::new(5., 5.));
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(Vec2::new(1., 1.).distance(Vec2::new(4., 5.)), 5.);
+        assert_eq!(Vec2::new(1., 1.).distance(Vec2::new(1., 1.)), 0.);
+    }
+
+    #[test]
+    fn clamp() {
+        let dims = (100, 50);
+
+        assert_eq!(Vec2::new(-5., 60.).clamp(dims, 10.), Vec2::new(10., 40.));
+        assert_eq!(Vec2::new(95., 5.).clamp(dims, 10.), Vec2::new(90., 10.));
+        assert_eq!(Vec2::new(50., 25.).clamp(dims, 10.), Vec2::new(50., 25.));
+    }
+
+    #[test]
+    fn remap() {
+        let v = Vec2::new(500., 250.);
+        assert_eq!(v.remap((1000, 500), (100., 50.)), Vec2::new(50., 25.));
+    }
+
+    fn vector() -> impl Strategy<Value = Vec2> {
+        (-1e4f64..1e4, -1e4f64..1e4).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    proptest! {
+        #[test]
+        fn normalize_keeps_direction(v in vector()) {
+            prop_assume!(v.length() > EPSILON);
+            let unit = v.normalize();
+
+            prop_assert!((unit.length() - 1.).abs() < EPSILON);
+            prop_assert!(unit.dot(v) > 0.);
+            prop_assert!(unit.cross(v).abs() < 1e-6);
+        }
+
+        #[test]
+        fn normalize_is_idempotent(v in vector()) {
+            let unit = v.normalize();
+            prop_assert!(unit.normalize().distance(unit) < EPSILON);
+        }
+
+        #[test]
+        fn rotate_keeps_length(v in vector(), angle in -10f64..10.) {
+            let rotated = v.rotate(angle);
+
+            prop_assert!((rotated.length() - v.length()).abs() < 1e-6);
+            prop_assert!(rotated.rotate(-angle).distance(v) < 1e-6);
+        }
+
+        #[test]
+        fn rotate_turns_the_angle(v in vector(), angle in -PI..PI) {
+            prop_assume!(v.length() > 1.);
+            prop_assert!((v.rotate(angle).angle() - v.angle() - angle).sin().abs() < 1e-9);
+        }
+
+        #[test]
+        fn perp_is_a_quarter_turn(v in vector()) {
+            prop_assert_eq!(v.dot(v.perp()), 0.);
+            prop_assert_eq!(v.perp().perp(), -v);
+            prop_assert!(v.cross(v.perp()) >= 0.);
+        }
+
+        #[test]
+        fn cross_is_antisymmetric(a in vector(), b in vector()) {
+            prop_assert_eq!(a.cross(b), -b.cross(a));
+            prop_assert_eq!(a.cross(a), 0.);
+        }
+
+        #[test]
+        fn lerp_stays_between(a in vector(), b in vector(), t in 0f64..=1.) {
+            let p = a.lerp(b, t);
+            prop_assert!((p.distance(a) + p.distance(b) - a.distance(b)).abs() < 1e-6);
+        }
+
+        #[test]
+        fn clamp_stays_inside(
+            v in vector(),
+            dims in (21u32..5000, 21u32..5000),
+            padding in 0f64..10.,
+        ) {
+            let clamped = v.clamp(dims, padding);
+
+            prop_assert!(clamped.x >= padding && clamped.x <= dims.0 as f64 - padding);
+            prop_assert!(clamped.y >= padding && clamped.y <= dims.1 as f64 - padding);
+            prop_assert_eq!(clamped.clamp(dims, padding), clamped);
+        }
+
+        #[test]
+        fn clamp_leaves_inside_points(
+            dims in (21u32..5000, 21u32..5000),
+            padding in 0f64..10.,
+            t in (0f64..=1., 0f64..=1.),
+        ) {
+            let v = Vec2::new(
+                padding + t.0 * (dims.0 as f64 - 2. * padding),
+                padding + t.1 * (dims.1 as f64 - 2. * padding),
+            );
+            prop_assert_eq!(v.clamp(dims, padding), v);
+        }
     }
 }
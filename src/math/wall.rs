@@ -1,12 +1,165 @@
-use crate::math::vector::Vec2D;
+//! Walls, the segments rays hit, and the surfaces deciding what happens when they do
 
+use crate::{
+    color::Color,
+    lighting::Lightmap,
+    math::{segment::Segment, vector::Vec2},
+};
+
+/// How a wall lets light and rays through
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Surface {
+    /// Stops rays and hides whatever is behind it
+    Opaque,
+    /// Blended over whatever is behind it using the wall color's alpha
+    Translucent,
+    /// Solid bars `bar_width` wide every `spacing` units along the wall, see-through between them
+    Grate {
+        /// Width of each bar
+        bar_width: f64,
+        /// Distance from the start of one bar to the start of the next
+        spacing: f64,
+    },
+    /// Reflects rays, the wall color's alpha sets how strongly it tints the reflection
+    Mirror,
+    /// Rays and the player pass through and come out of the wall at index `target`
+    Portal {
+        /// Index into the map's walls of the portal rays come out of
+        target: usize,
+    },
+}
+impl Surface {
+    /// Whether rays carry on through it, hitting what's behind as well
+    pub fn is_see_through(&self) -> bool {
+        matches!(self, Self::Translucent | Self::Grate { .. })
+    }
+
+    /// Whether a ray meeting the wall `along` units from its start passes straight through
+    pub fn is_gap_at(&self, along: f64) -> bool {
+        match *self {
+            Self::Grate { bar_width, spacing } if spacing > 0. => along % spacing >= bar_width,
+            _ => false,
+        }
+    }
+}
+
+/// A straight wall between `a` and `b`, its front face being the side `normal` points out of
+#[derive(Debug)]
 pub struct Wall {
-    pub a: Vec2D,
-    pub b: Vec2D,
-    pub color_index: usize,
+    /// One end
+    pub a: Vec2,
+    /// The other end
+    pub b: Vec2,
+    /// Color before lighting, its alpha used by translucent walls and mirrors
+    pub color: Color,
+    /// What happens to rays meeting it
+    pub surface: Surface,
+    /// Multiple of the standard wall height
+    pub height: f64,
+    /// Light from the map's baked point lights, shadows included, sampled along its length. `None` on walls none
+    /// reach and on doors, which move
+    pub lightmap: Option<Lightmap>,
 }
 impl Wall {
-    pub fn new(a: Vec2D, b: Vec2D, color_index: usize) -> Self {
-        Self { a, b, color_index }
+    /// An opaque wall of standard height from `a` to `b`, unlit until a lightmap is baked for it
+    pub fn new(a: Vec2, b: Vec2, color: Color) -> Self {
+        Self {
+            a,
+            b,
+            color,
+            surface: Surface::Opaque,
+            height: 1.,
+            lightmap: None,
+        }
+    }
+
+    /// Whether a ray stops at this wall, rather than carrying on through or over it
+    pub fn blocks_ray(&self) -> bool {
+        match self.surface {
+            Surface::Mirror | Surface::Portal { .. } => true,
+            surface => !surface.is_see_through() && self.height >= 1.,
+        }
+    }
+
+    /// The segment between its ends
+    pub fn segment(&self) -> Segment {
+        Segment::new(self.a, self.b)
+    }
+
+    /// Unit vector perpendicular to the wall, pointing out of its front face
+    pub fn normal(&self) -> Vec2 {
+        self.segment().normal()
+    }
+
+    /// Shortest distance from `point` to anywhere on the wall
+    pub fn distance_to(&self, point: Vec2) -> f64 {
+        self.segment().distance_to(point)
+    }
+
+    /// How squarely the wall faces the x axis, 0 when it runs along x and 1 when it runs along y
+    pub fn x_facing(&self) -> f64 {
+        let direction = self.segment().direction().normalize();
+        direction.y * direction.y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::math::EPSILON;
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (-1000f64..1000., -1000f64..1000.).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn wall() -> impl Strategy<Value = Wall> {
+        (point(), point())
+            .prop_filter("walls have length", |(a, b)| a.distance(*b) > 1.)
+            .prop_map(|(a, b)| Wall::new(a, b, Color::WHITE))
+    }
+
+    proptest! {
+        #[test]
+        fn normal_is_unit_and_perpendicular(wall in wall()) {
+            let normal = wall.normal();
+
+            prop_assert!((normal.length() - 1.).abs() < EPSILON);
+            prop_assert!(normal.dot(wall.b - wall.a).abs() < 1e-6);
+        }
+
+        #[test]
+        fn swapping_ends_flips_the_normal(wall in wall()) {
+            let flipped = Wall::new(wall.b, wall.a, wall.color);
+            prop_assert!((wall.normal() + flipped.normal()).length() < EPSILON);
+        }
+
+        #[test]
+        fn x_facing_is_a_fraction(wall in wall()) {
+            let x_facing = wall.x_facing();
+            prop_assert!((0. ..=1. + EPSILON).contains(&x_facing));
+        }
+
+        #[test]
+        fn endpoints_are_on_the_wall(wall in wall()) {
+            prop_assert_eq!(wall.distance_to(wall.a), 0.);
+            prop_assert!(wall.distance_to(wall.b) < EPSILON);
+        }
+
+        #[test]
+        fn grate_alternates_bars_and_gaps(
+            bar_width in 0.5f64..5.,
+            gap in 0.5f64..20.,
+            bar in 0usize..20,
+            within in 0.01f64..0.99,
+        ) {
+            let spacing = bar_width + gap;
+            let grate = Surface::Grate { bar_width, spacing };
+            let start = bar as f64 * spacing;
+
+            prop_assert!(!grate.is_gap_at(start + within * bar_width));
+            prop_assert!(grate.is_gap_at(start + bar_width + within * gap));
+        }
     }
 }
@@ -0,0 +1,236 @@
+//! An immediate mode menu. Each frame, screens call a method per item, and act on what it returns, whether the
+//! item was chosen or changed. Input from the frame's events goes to `handle_event` first. Items are measured in
+//! the renderer's font as they're drawn, so the mouse is checked against where they were last frame
+
+use sdl2::{event::Event, keyboard::Keycode, mouse::MouseButton};
+
+use raycast::{
+    render::{Rect, Renderer},
+    Color,
+};
+
+const TITLE_SCALE: u32 = 6;
+const ITEM_SCALE: u32 = 3;
+/// Fraction of the screen's height above the title
+const TOP: f64 = 0.2;
+/// Pixels between one item and the next, and between the title and the first item
+const ITEM_GAP: u32 = 18;
+/// Darkens whatever's behind the menu
+const BACKGROUND: Color = Color::rgba(0, 0, 0, 176);
+const SELECTED_COLOR: Color = Color::YELLOW;
+
+/// What the player did since the last frame
+#[derive(Debug, Default)]
+struct Input {
+    /// Items to move the selection by, negative going up
+    moves: i32,
+    /// Steps to change the selected item's value by, negative going left
+    adjust: i32,
+    activate: bool,
+    /// Where the mouse was clicked
+    click: Option<(i32, i32)>,
+    /// Whether the mouse moved, so hovering over an item should select it
+    mouse_moved: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Menu {
+    /// Index of the item the keyboard and mouse have picked out
+    selected: usize,
+    /// Items there were last frame, to wrap the selection around
+    count: usize,
+    mouse: Option<(i32, i32)>,
+    input: Input,
+    title: String,
+    /// Labels of the items so far this frame
+    items: Vec<String>,
+    /// Where each item was drawn last frame
+    rects: Vec<Rect>,
+}
+impl Menu {
+    /// Take in an event, with mouse positions in the same pixels the menu's drawn in
+    pub fn handle_event(&mut self, event: &Event, mouse_scale: (f64, f64)) {
+        let scaled = |x: i32, y: i32| {
+            (
+                (x as f64 * mouse_scale.0) as i32,
+                (y as f64 * mouse_scale.1) as i32,
+            )
+        };
+
+        match *event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Up | Keycode::W => self.input.moves -= 1,
+                Keycode::Down | Keycode::S => self.input.moves += 1,
+                Keycode::Left | Keycode::A => self.input.adjust -= 1,
+                Keycode::Right | Keycode::D => self.input.adjust += 1,
+                Keycode::Return | Keycode::KpEnter | Keycode::Space => self.input.activate = true,
+                _ => {}
+            },
+            Event::MouseMotion { x, y, .. } => {
+                self.mouse = Some(scaled(x, y));
+                self.input.mouse_moved = true;
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => self.input.click = Some(scaled(x, y)),
+            _ => {}
+        }
+    }
+
+    /// Start laying out a menu called `title`
+    pub fn begin(&mut self, title: &str) {
+        if self.count > 0 {
+            let count = self.count as i32;
+            self.selected = (self.selected as i32 + self.input.moves).rem_euclid(count) as usize;
+        }
+        self.title = title.to_owned();
+        self.items.clear();
+    }
+
+    /// Forget the selection, for when another screen of the menu's shown
+    pub fn reset(&mut self) {
+        self.selected = 0;
+        self.count = 0;
+        self.input = Input::default();
+        self.rects.clear();
+    }
+
+    pub fn button(&mut self, label: &str) -> bool {
+        let (selected, click) = self.item(label.to_owned());
+        click.is_some() || (selected && self.input.activate)
+    }
+
+    /// A switch showing whether `on` is, flipping it when chosen and returning whether it did
+    pub fn toggle(&mut self, label: &str, on: &mut bool) -> bool {
+        let text = format!("{}: {}", label, if *on { "on" } else { "off" });
+        let (selected, click) = self.item(text);
+        let flipped =
+            click.is_some() || (selected && (self.input.activate || self.input.adjust != 0));
+        if flipped {
+            *on = !*on;
+        }
+        flipped
+    }
+
+    /// `value` with arrows either side, stepping it through `range` by `step` with the left and right keys, or
+    /// by clicking either side of the middle. `decimals` is how many to show. Returns whether it changed
+    pub fn slider(
+        &mut self,
+        label: &str,
+        value: &mut f64,
+        range: (f64, f64),
+        step: f64,
+        decimals: usize,
+    ) -> bool {
+        let text = format!("{}: < {:.*} >", label, decimals, value);
+        let adjust = self.adjustment(text);
+        if adjust == 0 {
+            return false;
+        }
+        // Kept to whole steps, so stepping back and forth returns to the same values
+        let stepped = (((*value / step).round() + adjust as f64) * step).clamp(range.0, range.1);
+        let changed = stepped != *value;
+        *value = stepped;
+        changed
+    }
+
+    /// One of a set of options, `value` being the current one. Returns which way to step through them, if at all
+    pub fn choice(&mut self, label: &str, value: &str) -> i32 {
+        let text = format!("{}: < {} >", label, value);
+        self.adjustment(text)
+    }
+
+    /// How much an adjustable item's been stepped by, choosing it counting as a step forwards
+    fn adjustment(&mut self, text: String) -> i32 {
+        let idx = self.items.len();
+        let (selected, click) = self.item(text);
+        let middle = self
+            .rects
+            .get(idx)
+            .map_or(0, |rect| rect.x + rect.width as i32 / 2);
+        match click {
+            Some((x, _)) if x < middle => -1,
+            Some(_) => 1,
+            None if selected && self.input.adjust != 0 => self.input.adjust.signum(),
+            None if selected && self.input.activate => 1,
+            None => 0,
+        }
+    }
+
+    /// Add the next item, returning whether it's selected and where it was clicked
+    fn item(&mut self, text: String) -> (bool, Option<(i32, i32)>) {
+        let idx = self.items.len();
+        self.items.push(text);
+        // Not drawn yet, so there's nothing to point at
+        let rect = match self.rects.get(idx) {
+            Some(&rect) => rect,
+            None => return (self.selected == idx, None),
+        };
+        let over = |(x, y): (i32, i32)| {
+            x >= rect.x
+                && y >= rect.y
+                && x < rect.x + rect.width as i32
+                && y < rect.y + rect.height as i32
+        };
+
+        if self.input.mouse_moved && self.mouse.is_some_and(over) {
+            self.selected = idx;
+        }
+        let click = self.input.click.filter(|&point| over(point));
+        if click.is_some() {
+            self.selected = idx;
+        }
+
+        (self.selected == idx, click)
+    }
+
+    /// Finish the frame's layout and draw it over everything else
+    pub fn end<R: Renderer>(&mut self, renderer: &mut R) -> Result<(), String> {
+        self.count = self.items.len();
+        self.selected = self.selected.min(self.count.saturating_sub(1));
+        self.input = Input::default();
+
+        let (width, height) = renderer.size();
+        renderer.fill_rect(Rect::new(0, 0, width, height), BACKGROUND)?;
+
+        let top = (height as f64 * TOP) as i32;
+        let (title_width, title_height) = renderer.text_size(&self.title, TITLE_SCALE);
+        renderer.draw_text(
+            &self.title,
+            (width as i32 - title_width as i32) / 2,
+            top,
+            TITLE_SCALE,
+            Color::WHITE,
+        )?;
+
+        // Every item's as tall as a line, whether or not it has anything hanging below it
+        let (_, item_height) = renderer.text_size("", ITEM_SCALE);
+        let mut y = top + (title_height + ITEM_GAP * 2) as i32;
+        self.rects.clear();
+        for (idx, text) in self.items.iter().enumerate() {
+            let (item_width, _) = renderer.text_size(text, ITEM_SCALE);
+            let rect = Rect::new(
+                (width as i32 - item_width as i32) / 2,
+                y,
+                item_width,
+                item_height,
+            );
+            let color = if idx == self.selected {
+                SELECTED_COLOR
+            } else {
+                Color::WHITE
+            };
+            renderer.draw_text(text, rect.x, rect.y, ITEM_SCALE, color)?;
+            self.rects.push(rect);
+            y += (item_height + ITEM_GAP) as i32;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Drawing onto SDL canvases
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use sdl2::{
+    pixels::PixelFormatEnum,
+    render::{Canvas, RenderTarget, Texture, TextureCreator},
+    surface::Surface,
+    ttf::{self, Font, Sdl2TtfContext},
+    video::Window,
+};
+
+use super::{
+    font as bitmap_font,
+    framebuffer::Framebuffer,
+    renderer::{self, Rect, Renderer},
+};
+use crate::{color::Color, image::Image, math::vector::Vec2};
+
+/// A TrueType font canvases draw text in, loaded at each scale it's drawn at
+struct TtfFont {
+    context: &'static Sdl2TtfContext,
+    path: PathBuf,
+    /// Point size at a scale of 1
+    point_size: u16,
+    sizes: HashMap<u32, Font<'static, 'static>>,
+}
+
+thread_local! {
+    static FONT: RefCell<Option<TtfFont>> = const { RefCell::new(None) };
+}
+
+/// Draw text on canvases in the TrueType font at `path` instead of the built in bitmap font, `point_size` being its
+/// size at a scale of 1. Canvases fall back to the bitmap font until this succeeds
+pub fn load_font<P: AsRef<Path>>(path: P, point_size: u16) -> Result<(), String> {
+    FONT.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let context = match cell.as_ref() {
+            Some(font) => font.context,
+            // Fonts borrow the context, and live as long as the thread does
+            None => Box::leak(Box::new(ttf::init().map_err(|e| e.to_string())?)),
+        };
+
+        let path = path.as_ref().to_owned();
+        let font = context.load_font(&path, point_size)?;
+        *cell = Some(TtfFont {
+            context,
+            path,
+            point_size,
+            sizes: HashMap::from([(1, font)]),
+        });
+        Ok(())
+    })
+}
+
+/// Call `f` with the loaded font at `scale`, or return `None` if there isn't one
+fn with_font<R>(scale: u32, f: impl FnOnce(&Font<'static, 'static>) -> R) -> Option<R> {
+    FONT.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let font = cell.as_mut()?;
+        if !font.sizes.contains_key(&scale) {
+            let point_size = (font.point_size as u32 * scale).min(u16::MAX as u32) as u16;
+            let sized = font.context.load_font(&font.path, point_size).ok()?;
+            font.sizes.insert(scale, sized);
+        }
+        font.sizes.get(&scale).map(f)
+    })
+}
+
+/// Canvases that can draw surfaces, such as rendered text. SDL's bindings only make textures for each kind of
+/// render target separately, so this is what lets `Renderer` draw text on canvases of either
+pub trait CopySurface {
+    /// Copy `surface` onto `dest`, its alpha multiplied by `alpha`
+    fn copy_surface(&mut self, surface: &Surface, dest: Rect, alpha: u8) -> Result<(), String>;
+}
+impl CopySurface for Canvas<Window> {
+    fn copy_surface(&mut self, surface: &Surface, dest: Rect, alpha: u8) -> Result<(), String> {
+        let texture_creator = self.texture_creator();
+        copy_surface(self, &texture_creator, surface, dest, alpha)
+    }
+}
+impl CopySurface for Canvas<Surface<'_>> {
+    fn copy_surface(&mut self, surface: &Surface, dest: Rect, alpha: u8) -> Result<(), String> {
+        let texture_creator = self.texture_creator();
+        copy_surface(self, &texture_creator, surface, dest, alpha)
+    }
+}
+
+fn copy_surface<T: RenderTarget, C>(
+    canvas: &mut Canvas<T>,
+    texture_creator: &TextureCreator<C>,
+    surface: &Surface,
+    dest: Rect,
+    alpha: u8,
+) -> Result<(), String> {
+    let mut texture = texture_creator
+        .create_texture_from_surface(surface)
+        .map_err(|e| e.to_string())?;
+    texture.set_alpha_mod(alpha);
+    canvas.copy(&texture, None, sdl2::rect::Rect::from(dest))
+}
+
+/// Pixels from the top of one line of `font` to the next, leaving the same gap as the bitmap font does
+fn line_step(font: &Font, scale: u32) -> u32 {
+    font.height().max(0) as u32 + (bitmap_font::LINE_HEIGHT - bitmap_font::GLYPH_HEIGHT) * scale
+}
+
+/// Upload an image, such as a map's sky, so it can be blitted onto canvases
+pub fn texture_from_image<'a, T>(
+    texture_creator: &'a TextureCreator<T>,
+    image: &Image,
+) -> Result<Texture<'a>, String> {
+    let mut texture = texture_creator
+        .create_texture_static(PixelFormatEnum::RGBA32, image.width, image.height)
+        .map_err(|e| e.to_string())?;
+    texture
+        .update(None, &image.pixels, image.width as usize * 4)
+        .map_err(|e| e.to_string())?;
+    Ok(texture)
+}
+
+/// Read back everything drawn onto `canvas` since it was last presented
+pub fn read_image<T: RenderTarget>(canvas: &Canvas<T>) -> Result<Image, String> {
+    let (width, height) = canvas.output_size()?;
+    let pixels = canvas.read_pixels(None, PixelFormatEnum::RGBA32)?;
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// A texture a `Framebuffer` is uploaded to every frame, so the whole thing reaches the canvas in one copy
+/// rather than a draw call per column
+pub struct StreamingTexture<'a> {
+    texture: Texture<'a>,
+    width: u32,
+    height: u32,
+}
+impl<'a> StreamingTexture<'a> {
+    /// A `width` by `height` texture to stream frames into
+    pub fn new<T>(
+        texture_creator: &'a TextureCreator<T>,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, String> {
+        let texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            texture,
+            width,
+            height,
+        })
+    }
+
+    /// Replace the texture's pixels with the framebuffer's, which has to be the same size
+    pub fn upload(&mut self, framebuffer: &Framebuffer) -> Result<(), String> {
+        let image = framebuffer.image();
+        if (image.width, image.height) != (self.width, self.height) {
+            return Err(format!(
+                "Can't upload a {}x{} framebuffer to a {}x{} texture",
+                image.width, image.height, self.width, self.height
+            ));
+        }
+
+        self.texture
+            .update(None, &image.pixels, image.width as usize * 4)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Stretch the last upload over all of `canvas`
+    pub fn present<T: RenderTarget>(&self, canvas: &mut Canvas<T>) -> Result<(), String> {
+        canvas.copy(&self.texture, None, None)
+    }
+}
+
+impl From<Rect> for sdl2::rect::Rect {
+    fn from(this: Rect) -> Self {
+        Self::new(this.x, this.y, this.width, this.height)
+    }
+}
+
+/// Blending follows the canvas's blend mode, which needs to be `BlendMode::Blend` for translucent colors
+impl<T: RenderTarget> Renderer for Canvas<T>
+where
+    Self: CopySurface,
+{
+    type Texture<'a> = Texture<'a>;
+
+    fn size(&self) -> (u32, u32) {
+        let viewport = self.viewport();
+        (viewport.width(), viewport.height())
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), String> {
+        self.set_draw_color(color);
+        Canvas::clear(self);
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        self.set_draw_color(color);
+        Canvas::fill_rect(self, sdl2::rect::Rect::from(rect))
+    }
+
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color) -> Result<(), String> {
+        self.set_draw_color(color);
+        Canvas::draw_line(self, from, to)
+    }
+
+    fn blit(&mut self, texture: &Texture<'_>, src: Rect, dest: Rect) -> Result<(), String> {
+        self.copy(
+            texture,
+            sdl2::rect::Rect::from(src),
+            sdl2::rect::Rect::from(dest),
+        )
+    }
+
+    /// In the font given to `load_font`, or the bitmap font if there isn't one
+    fn draw_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        let drawn = with_font(scale, |font| -> Result<(), String> {
+            let step = line_step(font, scale) as i32;
+            for (idx, line) in text.split('\n').enumerate() {
+                // Nothing to render, and SDL_ttf refuses to
+                if line.is_empty() {
+                    continue;
+                }
+                let surface = font
+                    .render(line)
+                    .blended(Color::rgb(color.r, color.g, color.b))
+                    .map_err(|e| e.to_string())?;
+                let dest = Rect::new(x, y + idx as i32 * step, surface.width(), surface.height());
+                self.copy_surface(&surface, dest, color.a)?;
+            }
+            Ok(())
+        });
+
+        match drawn {
+            Some(result) => result,
+            None => renderer::draw_bitmap_text(self, text, x, y, scale, color),
+        }
+    }
+
+    fn text_size(&self, text: &str, scale: u32) -> (u32, u32) {
+        let measured = with_font(scale, |font| {
+            let lines = text.split('\n');
+            let (width, count) = lines.fold((0, 0), |(width, count), line| {
+                let (line_width, _) = font.size_of(line).unwrap_or((0, 0));
+                (width.max(line_width), count + 1)
+            });
+            let step = line_step(font, scale);
+            (width, (count - 1) * step + font.height().max(0) as u32)
+        });
+
+        measured.unwrap_or_else(|| renderer::text_size(text, scale))
+    }
+}
@@ -0,0 +1,118 @@
+use super::{
+    renderer::{Rect, Renderer},
+    Frame,
+};
+use crate::{color::Color, image::Image, lighting::Illumination, map::Map};
+
+/// Draw the ceiling, floor, sky and walls of `frame`, which was cast into `map`.
+/// `sky` is the map's sky made into a texture for `renderer`
+pub fn draw_frame<R: Renderer>(
+    renderer: &mut R,
+    frame: &Frame,
+    map: &Map,
+    sky: Option<&R::Texture<'_>>,
+) -> Result<(), String> {
+    let horizon = frame.viewport.horizon();
+
+    draw_background(renderer, frame, map)?;
+    if let (Some(texture), Some(image)) = (sky, &map.sky) {
+        draw_sky(renderer, frame, texture, image)?;
+    }
+
+    for (idx, column) in frame.columns.iter().enumerate() {
+        let (x, width) = frame.viewport.column_span(idx);
+        if width == 0 {
+            continue;
+        }
+
+        // Back to front, so see-through walls blend over what's behind them
+        for hit in column.hits.iter().rev() {
+            let color = map
+                .lighting
+                .shade(hit.color, hit.distance, hit.x_facing, hit.illumination);
+            let bottom = horizon + (hit.height / 2) as i32;
+            let height = (hit.height as f64 * hit.wall_height).round() as u32;
+            renderer.fill_column(x, bottom - height as i32, width, height, color)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Ceiling and floor, shaded by the distance each row of pixels lies at
+fn draw_background<R: Renderer>(renderer: &mut R, frame: &Frame, map: &Map) -> Result<(), String> {
+    let viewport = frame.viewport;
+    let half_height = viewport.horizon();
+    let projection_factor = frame.projection_factor();
+    let background = map.background;
+
+    // Rows sharing a shade are drawn together
+    let mut band_start = 0;
+    let mut band_shades: Option<(Color, Color)> = None;
+
+    for row in 0..=half_height {
+        let shades = if row == half_height {
+            None
+        } else {
+            // A wall whose bottom edge lands `offset` pixels from the horizon is this far away
+            let offset = (half_height - row) as f64;
+            let distance = projection_factor / (2. * offset);
+            let lighting = map.lighting;
+            Some((
+                lighting.shade(background.ceiling, distance, 0., Illumination::NONE),
+                lighting.shade(background.floor, distance, 0., Illumination::NONE),
+            ))
+        };
+
+        if shades == band_shades {
+            continue;
+        }
+
+        if let Some((ceiling, floor)) = band_shades {
+            let band_height = (row - band_start) as u32;
+            // The sky covers the ceiling
+            if map.sky.is_none() {
+                renderer.fill_rect(
+                    Rect::new(0, band_start, viewport.width, band_height),
+                    ceiling,
+                )?;
+            }
+            renderer.fill_rect(
+                Rect::new(0, viewport.height as i32 - row, viewport.width, band_height),
+                floor,
+            )?;
+        }
+
+        band_start = row;
+        band_shades = shades;
+    }
+
+    Ok(())
+}
+
+/// The map's sky panorama above the horizon, the part in view scrolling with the camera's angle.
+/// Walls are drawn over it afterwards
+fn draw_sky<R: Renderer>(
+    renderer: &mut R,
+    frame: &Frame,
+    texture: &R::Texture<'_>,
+    image: &Image,
+) -> Result<(), String> {
+    let viewport = frame.viewport;
+    let turn = std::f64::consts::PI * 2.;
+
+    // Each column shows the slice of the panorama at the angle its ray was cast at, wrapping around
+    for idx in 0..viewport.columns {
+        let (x, width) = viewport.column_span(idx);
+        let angle = (frame.camera.angle + frame.column_angle(idx)).rem_euclid(turn);
+        let src_x = ((angle / turn * image.width as f64) as u32).min(image.width - 1);
+
+        renderer.blit(
+            texture,
+            Rect::new(src_x as i32, 0, 1, image.height),
+            Rect::new(x, 0, width, viewport.height / 2),
+        )?;
+    }
+
+    Ok(())
+}
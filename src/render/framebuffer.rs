@@ -0,0 +1,155 @@
+use super::renderer::{Rect, Renderer};
+use crate::{color::Color, image::Image, math::vector::Vec2};
+
+/// Renders in memory on the CPU, for when there's no window to draw to.
+/// There's no font rasterizer on the CPU, so text is always in the built in bitmap font
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    image: Image,
+}
+impl Framebuffer {
+    /// A `width` by `height` framebuffer cleared to black
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            image: Image::new(width, height, Color::BLACK),
+        }
+    }
+
+    /// What's been drawn so far
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Take what's been drawn, done drawing
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Blend `color` over the pixel at `(x, y)`, doing nothing outside the framebuffer
+    fn blend(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x as u32 >= self.image.width || y as u32 >= self.image.height {
+            return;
+        }
+
+        let idx = (y as usize * self.image.width as usize + x as usize) * 4;
+        let pixel = &mut self.image.pixels[idx..idx + 4];
+        blend_over(pixel, color);
+    }
+}
+
+/// Alpha blend `color` over an RGBA `pixel` in place
+fn blend_over(pixel: &mut [u8], color: Color) {
+    match color.a {
+        255 => pixel.copy_from_slice(&[color.r, color.g, color.b, 255]),
+        0 => {}
+        alpha => {
+            let alpha = alpha as f64 / 255.;
+            let mix =
+                |src: u8, dst: u8| (src as f64 * alpha + dst as f64 * (1. - alpha)).round() as u8;
+            pixel[0] = mix(color.r, pixel[0]);
+            pixel[1] = mix(color.g, pixel[1]);
+            pixel[2] = mix(color.b, pixel[2]);
+            pixel[3] = mix(255, pixel[3]);
+        }
+    }
+}
+
+impl Renderer for Framebuffer {
+    type Texture<'a> = Image;
+
+    fn size(&self) -> (u32, u32) {
+        (self.image.width, self.image.height)
+    }
+
+    fn clear(&mut self, color: Color) -> Result<(), String> {
+        let color = [color.r, color.g, color.b, color.a];
+        for pixel in self.image.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String> {
+        let (width, height) = self.size();
+        // Walls right up against the camera can be far taller than the screen
+        let clip = |start: i32, length: u32, max: u32| {
+            let start = start as i64;
+            (
+                start.clamp(0, max as i64) as u32,
+                (start + length as i64).clamp(0, max as i64) as u32,
+            )
+        };
+        let (left, right) = clip(rect.x, rect.width, width);
+        let (top, bottom) = clip(rect.y, rect.height, height);
+        if left >= right {
+            return Ok(());
+        }
+
+        for y in top..bottom {
+            let row = (y * width) as usize * 4;
+            for pixel in self.image.pixels[row + left as usize * 4..row + right as usize * 4]
+                .chunks_exact_mut(4)
+            {
+                blend_over(pixel, color);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color) -> Result<(), String> {
+        // Bresenham's, stepping one pixel at a time along the longer axis
+        let (mut x, mut y) = (from.x.round() as i32, from.y.round() as i32);
+        let (end_x, end_y) = (to.x.round() as i32, to.y.round() as i32);
+        let dx = (end_x - x).abs();
+        let dy = -(end_y - y).abs();
+        let step_x = if x < end_x { 1 } else { -1 };
+        let step_y = if y < end_y { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            self.blend(x, y, color);
+            if x == end_x && y == end_y {
+                break;
+            }
+
+            let doubled = error * 2;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn blit(&mut self, texture: &Image, src: Rect, dest: Rect) -> Result<(), String> {
+        if dest.width == 0 || dest.height == 0 {
+            return Ok(());
+        }
+
+        // Nearest neighbour, sampling the middle of each destination pixel
+        let x_scale = src.width as f64 / dest.width as f64;
+        let y_scale = src.height as f64 / dest.height as f64;
+
+        for dest_y in 0..dest.height {
+            let src_y = src.y + ((dest_y as f64 + 0.5) * y_scale) as i32;
+            for dest_x in 0..dest.width {
+                let src_x = src.x + ((dest_x as f64 + 0.5) * x_scale) as i32;
+                if src_x < 0 || src_y < 0 {
+                    continue;
+                }
+
+                if let Some(color) = texture.pixel(src_x as u32, src_y as u32) {
+                    self.blend(dest.x + dest_x as i32, dest.y + dest_y as i32, color);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
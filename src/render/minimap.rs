@@ -0,0 +1,297 @@
+use std::f64::consts::FRAC_PI_2;
+
+use super::{
+    renderer::{Rect, Renderer},
+    Frame,
+};
+use crate::{color::Color, map::Map, math::vector::Vec2};
+
+/// Side of the square marking the player, in pixels
+const PLAYER_SIZE: u32 = 8;
+/// Side of the squares marking where rays hit, in pixels
+const HIT_SIZE: u32 = 2;
+const HIT_COLOR: Color = Color::RED;
+/// Color the highlighted column's walls and hits are drawn in
+const HIGHLIGHT_COLOR: Color = Color::YELLOW;
+/// How far the minimap can zoom out and in
+const ZOOM_RANGE: (f64, f64) = (0.25, 16.);
+
+/// Which corner of the screen the minimap sits in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    /// Top left corner
+    TopLeft,
+    /// Top right corner
+    TopRight,
+    /// Bottom left corner
+    BottomLeft,
+    /// Bottom right corner
+    BottomRight,
+}
+
+/// A top down view of the map and the player's sight lines, in a corner of the screen or filling it as an automap
+#[derive(Debug, Clone)]
+pub struct Minimap {
+    /// Which corner it sits in, unless it's an automap
+    pub corner: Corner,
+    /// Length of its sides as a fraction of the screen's shorter side
+    pub size: f64,
+    /// Pixels between it and the edges of the screen
+    pub margin: u32,
+    /// Fill the screen rather than a corner of it
+    pub automap: bool,
+    /// Magnification, 1 fitting the whole map in
+    pub zoom: f64,
+    /// Map units the view is moved from the map's centre. Ignored while following the player
+    pub pan: Vec2,
+    /// Centre on the player, turning with them so they always face up
+    pub follow_player: bool,
+    /// Only show walls the player has seen
+    pub fog_of_war: bool,
+    /// Mark where every column's ray hit
+    pub show_hits: bool,
+    /// Column of the frame whose walls and hits stand out, such as one being inspected
+    pub highlighted_column: Option<usize>,
+    /// Color behind the walls
+    pub background: Color,
+    /// Whether each wall has been seen, by index
+    seen: Vec<bool>,
+}
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            corner: Corner::TopLeft,
+            size: 0.35,
+            margin: 32,
+            automap: false,
+            zoom: 1.,
+            pan: Vec2::ZERO,
+            follow_player: false,
+            fog_of_war: false,
+            show_hits: false,
+            highlighted_column: None,
+            background: Color::BLACK,
+            seen: vec![],
+        }
+    }
+}
+impl Minimap {
+    /// Where it's drawn on a `screen` sized renderer
+    pub fn rect(&self, screen: (u32, u32)) -> Rect {
+        let (width, height) = screen;
+        if self.automap {
+            return Rect::new(0, 0, width, height);
+        }
+
+        let side = (width.min(height) as f64 * self.size).round() as u32;
+        let left = self.margin as i32;
+        let right = width as i32 - self.margin as i32 - side as i32;
+        let top = self.margin as i32;
+        let bottom = height as i32 - self.margin as i32 - side as i32;
+        let (x, y) = match self.corner {
+            Corner::TopLeft => (left, top),
+            Corner::TopRight => (right, top),
+            Corner::BottomLeft => (left, bottom),
+            Corner::BottomRight => (right, bottom),
+        };
+        Rect::new(x, y, side, side)
+    }
+
+    /// Zoom in by `factor`, or out when it's below 1
+    pub fn zoom_by(&mut self, factor: f64) {
+        self.zoom = (self.zoom * factor).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+    }
+
+    /// Mark every wall `frame` hit as seen, for fog of war
+    pub fn reveal(&mut self, frame: &Frame, map: &Map) {
+        self.seen.resize(map.walls.len(), false);
+        for hit in frame.columns.iter().flat_map(|column| column.hits.iter()) {
+            self.seen[hit.wall_index] = true;
+        }
+    }
+
+    /// Forget which walls have been seen, for when another map is loaded
+    pub fn forget(&mut self) {
+        self.seen.clear();
+    }
+
+    /// Whether the wall at `wall_index` has been seen
+    pub fn seen(&self, wall_index: usize) -> bool {
+        self.seen.get(wall_index).copied().unwrap_or(false)
+    }
+
+    /// Draw `map` with the camera and hits of `frame` on top
+    pub fn draw<R: Renderer>(
+        &self,
+        renderer: &mut R,
+        frame: &Frame,
+        map: &Map,
+    ) -> Result<(), String> {
+        let rect = self.rect(renderer.size());
+        let projection = self.projection(rect, frame, map);
+        renderer.fill_rect(rect, self.background)?;
+
+        // Walls
+        for (idx, wall) in map.walls.iter().enumerate() {
+            if self.fog_of_war && !self.seen(idx) {
+                continue;
+            }
+            projection.draw_line(renderer, wall.a, wall.b, wall.color)?;
+        }
+
+        // Where each column's ray hit
+        if self.show_hits {
+            for hit in frame.columns.iter().flat_map(|column| column.hits.iter()) {
+                let point = projection.to_screen(hit.point);
+                if projection.contains(point) {
+                    renderer.fill_rect(centred_square(point, HIT_SIZE), HIT_COLOR)?;
+                }
+            }
+        }
+
+        // The highlighted column's walls and hits, whether they've been seen or not
+        let highlighted = self
+            .highlighted_column
+            .and_then(|idx| frame.columns.get(idx));
+        for hit in highlighted.iter().flat_map(|column| column.hits.iter()) {
+            let wall = &map.walls[hit.wall_index];
+            projection.draw_line(renderer, wall.a, wall.b, HIGHLIGHT_COLOR)?;
+            let point = projection.to_screen(hit.point);
+            if projection.contains(point) {
+                renderer.fill_rect(centred_square(point, HIT_SIZE * 2), HIGHLIGHT_COLOR)?;
+            }
+        }
+
+        // Player, and lines forwards and along the edges of their view
+        let camera = frame.camera;
+        let sight_length = rect.width.max(rect.height) as f64 / 2. / projection.scale;
+        let half_fov = camera.fov.to_radians() / 2.;
+        for angle in [
+            camera.angle,
+            camera.angle - half_fov,
+            camera.angle + half_fov,
+        ] {
+            let end = camera.position + Vec2::from_angle(angle) * sight_length;
+            projection.draw_line(renderer, camera.position, end, Color::WHITE)?;
+        }
+        let position = projection.to_screen(camera.position);
+        if projection.contains(position) {
+            renderer.fill_rect(centred_square(position, PLAYER_SIZE), Color::WHITE)?;
+        }
+
+        Ok(())
+    }
+
+    fn projection(&self, rect: Rect, frame: &Frame, map: &Map) -> Projection {
+        let dims = Vec2::new(map.dims.0 as f64, map.dims.1 as f64);
+        let fit = (rect.width as f64 / dims.x).min(rect.height as f64 / dims.y);
+        let camera = frame.camera;
+
+        let (centre, rotation) = if self.follow_player {
+            (camera.position, -FRAC_PI_2 - camera.angle)
+        } else {
+            (dims / 2. + self.pan, 0.)
+        };
+
+        Projection {
+            rect,
+            centre,
+            rotation,
+            scale: fit * self.zoom,
+        }
+    }
+}
+
+/// Takes points in the map onto the minimap
+struct Projection {
+    rect: Rect,
+    /// Point in the map at the middle of the minimap
+    centre: Vec2,
+    /// Radians the map is turned by
+    rotation: f64,
+    /// Pixels per map unit
+    scale: f64,
+}
+impl Projection {
+    fn to_screen(&self, point: Vec2) -> Vec2 {
+        let middle = Vec2::new(
+            self.rect.x as f64 + self.rect.width as f64 / 2.,
+            self.rect.y as f64 + self.rect.height as f64 / 2.,
+        );
+        middle + (point - self.centre).rotate(self.rotation) * self.scale
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = self.rect;
+        point.x >= x as f64
+            && point.y >= y as f64
+            && point.x <= (x + width as i32) as f64
+            && point.y <= (y + height as i32) as f64
+    }
+
+    /// Draw the part of the line from `from` to `to` in the map that lands inside the minimap
+    fn draw_line<R: Renderer>(
+        &self,
+        renderer: &mut R,
+        from: Vec2,
+        to: Vec2,
+        color: Color,
+    ) -> Result<(), String> {
+        match self.clip(self.to_screen(from), self.to_screen(to)) {
+            Some((from, to)) => renderer.draw_line(from, to, color),
+            None => Ok(()),
+        }
+    }
+
+    /// The part of the line from `from` to `to` inside the minimap, by Liang-Barsky
+    fn clip(&self, from: Vec2, to: Vec2) -> Option<(Vec2, Vec2)> {
+        let delta = to - from;
+        let (min_x, min_y) = (self.rect.x as f64, self.rect.y as f64);
+        let max_x = min_x + self.rect.width as f64;
+        let max_y = min_y + self.rect.height as f64;
+
+        let (mut enter, mut exit) = (0f64, 1f64);
+        // Each edge as how fast the line heads out past it and how far inside it the line starts
+        let edges = [
+            (-delta.x, from.x - min_x),
+            (delta.x, max_x - from.x),
+            (-delta.y, from.y - min_y),
+            (delta.y, max_y - from.y),
+        ];
+        for (outward, inside) in edges.iter().copied() {
+            if outward == 0. {
+                if inside < 0. {
+                    return None;
+                }
+                continue;
+            }
+
+            let t = inside / outward;
+            if outward < 0. {
+                enter = enter.max(t);
+            } else {
+                exit = exit.min(t);
+            }
+        }
+
+        if enter > exit {
+            return None;
+        }
+        Some((from + delta * enter, from + delta * exit))
+    }
+}
+
+fn centred_square(centre: Vec2, side: u32) -> Rect {
+    let half = side as f64 / 2.;
+    Rect::new(
+        (centre.x - half).round() as i32,
+        (centre.y - half).round() as i32,
+        side,
+        side,
+    )
+}
@@ -0,0 +1,313 @@
+//! Turning what a camera sees into columns of wall slices, and drawing them with any `Renderer`
+
+#[cfg(feature = "sdl")]
+pub mod canvas;
+mod draw;
+pub mod font;
+mod framebuffer;
+mod minimap;
+mod renderer;
+mod resolution;
+pub mod terminal;
+
+pub use draw::draw_frame;
+pub use framebuffer::Framebuffer;
+pub use minimap::{Corner, Minimap};
+pub use renderer::{draw_bitmap_text, text_size, Rect, Renderer};
+pub use resolution::DynamicResolution;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    caster::{self, RayHit},
+    color::Color,
+    lighting::Illumination,
+    map::Map,
+    math::vector::Vec2,
+};
+
+/// Height of a standard wall in map units
+pub const WALL_HEIGHT: f64 = 48.;
+
+/// Where the view is seen from
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// Where the camera is, in map units
+    pub position: Vec2,
+    /// Direction the camera faces, in radians
+    pub angle: f64,
+    /// Horizontal field of view, in degrees
+    pub fov: f64,
+}
+
+/// A field of view held fixed across one axis of the viewport, the other following its aspect ratio
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Fov {
+    /// Degrees across the viewport's width, so wider viewports see the same amount, stretched
+    Horizontal(f64),
+    /// Degrees across the viewport's height, so wider viewports see more to the sides
+    Vertical(f64),
+}
+impl Fov {
+    /// Horizontal field of view in degrees, for a viewport `aspect` times as wide as it is tall
+    pub fn horizontal(self, aspect: f64) -> f64 {
+        match self {
+            Self::Horizontal(degrees) => degrees,
+            Self::Vertical(degrees) => {
+                (2. * ((degrees.to_radians() / 2.).tan() * aspect).atan()).to_degrees()
+            }
+        }
+    }
+
+    /// Vertical field of view in degrees, for a viewport `aspect` times as wide as it is tall
+    pub fn vertical(self, aspect: f64) -> f64 {
+        match self {
+            Self::Horizontal(degrees) => {
+                (2. * ((degrees.to_radians() / 2.).tan() / aspect).atan()).to_degrees()
+            }
+            Self::Vertical(degrees) => degrees,
+        }
+    }
+
+    /// Held fixed across the other axis instead, seeing the same as now at `aspect`
+    pub fn switch_axis(self, aspect: f64) -> Self {
+        match self {
+            Self::Horizontal(_) => Self::Vertical(self.vertical(aspect)),
+            Self::Vertical(_) => Self::Horizontal(self.horizontal(aspect)),
+        }
+    }
+
+    /// Magnified `factor` times, as a lens would rather than by scaling the angle
+    pub fn zoomed(self, factor: f64) -> Self {
+        let zoom =
+            |degrees: f64| (2. * ((degrees.to_radians() / 2.).tan() / factor).atan()).to_degrees();
+        self.map(zoom)
+    }
+
+    /// The same kind of field of view, its degrees passed through `f`
+    pub fn map(self, f: impl FnOnce(f64) -> f64) -> Self {
+        match self {
+            Self::Horizontal(degrees) => Self::Horizontal(f(degrees)),
+            Self::Vertical(degrees) => Self::Vertical(f(degrees)),
+        }
+    }
+}
+
+/// The area of the screen the view is drawn into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// How many rays are cast across the width, one per column
+    pub columns: usize,
+}
+impl Viewport {
+    /// A viewport casting one ray per pixel of its width
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            columns: width as usize,
+        }
+    }
+
+    /// A `width` by `height` viewport casting `scale` times as many rays as it has pixels across,
+    /// so below 1 each column is drawn wider than a pixel
+    pub fn scaled(width: u32, height: u32, scale: f64) -> Self {
+        Self {
+            width,
+            height,
+            columns: ((width as f64 * scale).round() as usize).max(1),
+        }
+    }
+
+    /// Left edge and width in pixels of column `idx`.
+    /// The width is shared out as evenly as whole pixels allow, so some columns can be a pixel wider than others
+    pub fn column_span(&self, idx: usize) -> (i32, u32) {
+        let columns = self.columns.max(1) as u64;
+        let edge = |idx: usize| (idx as u64 * self.width as u64 / columns) as i32;
+        let left = edge(idx);
+        (left, (edge(idx + 1) - left) as u32)
+    }
+
+    /// Index of the column covering pixel `x`, `None` outside the viewport
+    pub fn column_at(&self, x: i32) -> Option<usize> {
+        if x < 0 || x as u32 >= self.width || self.columns == 0 {
+            return None;
+        }
+
+        // The last column whose left edge is at or before `x`
+        let (x, columns, width) = (x as u64, self.columns as u64, self.width as u64);
+        Some((((x + 1) * columns).div_ceil(width) - 1) as usize)
+    }
+
+    /// Row of pixels the horizon lies on
+    pub fn horizon(&self) -> i32 {
+        self.height as i32 / 2
+    }
+
+    /// Width over height
+    pub fn aspect(&self) -> f64 {
+        self.width as f64 / self.height.max(1) as f64
+    }
+}
+
+/// Everything a column's ray hit, nearest first. Only the last hit can be opaque
+#[derive(Debug, Clone, Default)]
+pub struct Column {
+    /// Every wall hit, nearest first
+    pub hits: Vec<Hit>,
+}
+
+/// A wall hit by a column's ray
+#[derive(Debug, Clone, Copy)]
+pub struct Hit {
+    /// Index of the wall in the map's walls
+    pub wall_index: usize,
+    /// Where on the wall the ray hit, in map units
+    pub point: Vec2,
+    /// Color of the wall before shading
+    pub color: Color,
+    /// Projected height of a standard height wall at this distance
+    pub height: u32,
+    /// Multiple of the standard height the wall actually is, it stands on the floor either way
+    pub wall_height: f64,
+    /// Distance to the wall, corrected for fisheye unless the frame's `correct_fisheye` is off
+    pub distance: f64,
+    /// Distance the ray travelled to the wall, through any mirrors and portals
+    pub raw_distance: f64,
+    /// How squarely the wall faces the x axis, for side shading
+    pub x_facing: f64,
+    /// Light falling on the wall where it was hit, on top of the ambient light
+    pub illumination: Illumination,
+}
+
+/// The columns of one view of a map, reused from frame to frame to avoid reallocating
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Where the view was last cast from
+    pub camera: Camera,
+    /// Area of the screen it's drawn into
+    pub viewport: Viewport,
+    /// One per ray cast across the viewport, left to right
+    pub columns: Vec<Column>,
+    /// Measure distances to the flat projection plane rather than the camera, so straight walls look straight.
+    /// Turning it off shows the fisheye distortion it corrects
+    pub correct_fisheye: bool,
+    ray_hits: Vec<RayHit>,
+}
+impl Frame {
+    /// An empty frame, filled in by `cast`
+    pub fn new(camera: Camera, viewport: Viewport) -> Self {
+        Self {
+            camera,
+            viewport,
+            columns: Vec::with_capacity(viewport.columns),
+            correct_fisheye: true,
+            ray_hits: vec![],
+        }
+    }
+
+    /// Distance in pixels from the camera to the projection plane the view is drawn on
+    fn projection_plane_distance(&self) -> f64 {
+        (self.viewport.width as f64 / 2.) / (self.camera.fov.to_radians() / 2.).tan()
+    }
+
+    /// Angle, relative to the camera's, of the ray cast through column `idx`.
+    /// Through the middle of the column on the projection plane, so columns are evenly spaced on screen
+    /// rather than in angle, keeping wide fields of view undistorted
+    pub fn column_angle(&self, idx: usize) -> f64 {
+        Self::ray_angle(self.viewport, self.projection_plane_distance(), idx)
+    }
+
+    fn ray_angle(viewport: Viewport, projection_plane_distance: f64, idx: usize) -> f64 {
+        let (x, width) = viewport.column_span(idx);
+        let offset = x as f64 + width as f64 / 2. - viewport.width as f64 / 2.;
+        (offset / projection_plane_distance).atan()
+    }
+
+    /// Scale turning a distance into the projected height of a standard wall at that distance
+    pub fn projection_factor(&self) -> f64 {
+        self.projection_plane_distance() * WALL_HEIGHT
+    }
+
+    /// Cast a ray per column from `camera` into `map`, `time` in seconds driving flickering lights
+    pub fn cast(&mut self, map: &Map, camera: Camera, time: f64) {
+        self.camera = camera;
+
+        let projection_factor = self.projection_factor();
+        let projection_plane_distance = self.projection_plane_distance();
+        let correct_fisheye = self.correct_fisheye;
+        let viewport = self.viewport;
+
+        let ray_hits = &mut self.ray_hits;
+        self.columns.resize_with(viewport.columns, Column::default);
+
+        for (idx, column) in self.columns.iter_mut().enumerate() {
+            let delta = Self::ray_angle(viewport, projection_plane_distance, idx);
+            caster::cast(&map.walls, camera.position, camera.angle + delta, ray_hits);
+
+            column.hits.clear();
+            column.hits.extend(ray_hits.iter().map(|ray_hit| {
+                let wall = &map.walls[ray_hit.wall_index];
+                let corrected_distance = if correct_fisheye {
+                    ray_hit.distance * delta.cos()
+                } else {
+                    ray_hit.distance
+                };
+                let projected_height = projection_factor / corrected_distance;
+
+                Hit {
+                    wall_index: ray_hit.wall_index,
+                    point: ray_hit.point,
+                    color: wall.color,
+                    height: projected_height.round() as u32,
+                    wall_height: wall.height,
+                    distance: corrected_distance,
+                    raw_distance: ray_hit.distance,
+                    x_facing: wall.x_facing(),
+                    illumination: map.illumination_at(
+                        ray_hit.wall_index,
+                        ray_hit.point,
+                        ray_hit.eye,
+                        time,
+                    ),
+                }
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertical_fov_widens_with_the_aspect_ratio() {
+        let fov = Fov::Vertical(36.);
+        assert!((fov.horizontal(16. / 9.) - 60.).abs() < 0.1);
+        assert!(fov.horizontal(21. / 9.) > fov.horizontal(16. / 9.));
+        assert_eq!(Fov::Horizontal(60.).horizontal(21. / 9.), 60.);
+    }
+
+    #[test]
+    fn switching_axis_keeps_the_view() {
+        let aspect = 16. / 9.;
+        let switched = Fov::Vertical(36.).switch_axis(aspect);
+        assert!(matches!(switched, Fov::Horizontal(_)));
+        assert!((switched.vertical(aspect) - 36.).abs() < 1e-9);
+        assert!(
+            (switched.switch_axis(aspect).horizontal(aspect) - switched.horizontal(aspect)).abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn zooming_magnifies_the_projection() {
+        let zoomed = Fov::Horizontal(90.).zoomed(2.);
+        let expected = (2. * 0.5f64.atan()).to_degrees();
+        assert!(matches!(zoomed, Fov::Horizontal(degrees) if (degrees - expected).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,133 @@
+use super::font;
+use crate::{color::Color, math::vector::Vec2};
+
+/// An axis aligned rectangle of pixels, `(x, y)` being its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// Left edge
+    pub x: i32,
+    /// Top edge
+    pub y: i32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+impl Rect {
+    /// A `width` by `height` rectangle with its top left corner at `(x, y)`
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Something frames, maps and text can be drawn onto.
+/// Colors with an alpha below 255 are blended over what's already there
+pub trait Renderer {
+    /// An image ready for `blit`, borrowed for `'a` from whatever created it
+    type Texture<'a>;
+
+    /// Width and height in pixels
+    fn size(&self) -> (u32, u32);
+
+    /// Fill everything with `color`, ignoring its alpha
+    fn clear(&mut self, color: Color) -> Result<(), String>;
+
+    /// Fill `rect` with `color`
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<(), String>;
+
+    /// Draw a one pixel wide line from `from` to `to`
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color) -> Result<(), String>;
+
+    /// Copy the `src` part of `texture` stretched over `dest`
+    fn blit(&mut self, texture: &Self::Texture<'_>, src: Rect, dest: Rect) -> Result<(), String>;
+
+    /// A vertical strip `width` pixels wide, from `top` down `height` pixels
+    fn fill_column(
+        &mut self,
+        x: i32,
+        top: i32,
+        width: u32,
+        height: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        self.fill_rect(Rect::new(x, top, width, height), color)
+    }
+
+    /// `text` with its top left corner at `(x, y)`, `scale` times the size of the built in bitmap font.
+    /// Unless a renderer has a font of its own, that's the font it's drawn in, see `draw_bitmap_text`
+    fn draw_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        draw_bitmap_text(self, text, x, y, scale, color)
+    }
+
+    /// Width and height `text` takes up when drawn with `draw_text` at `scale`
+    fn text_size(&self, text: &str, scale: u32) -> (u32, u32) {
+        text_size(text, scale)
+    }
+}
+
+/// `text` in the built in bitmap font, its top left corner at `(x, y)` and each pixel of the font `scale` pixels wide.
+/// Lowercase letters are drawn as uppercase and characters the font doesn't have as `?`
+pub fn draw_bitmap_text<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    text: &str,
+    x: i32,
+    y: i32,
+    scale: u32,
+    color: Color,
+) -> Result<(), String> {
+    let scale_signed = scale as i32;
+    let (mut pen_x, mut pen_y) = (x, y);
+
+    for c in text.chars() {
+        if c == '\n' {
+            pen_x = x;
+            pen_y += font::LINE_HEIGHT as i32 * scale_signed;
+            continue;
+        }
+
+        for (row, bits) in font::glyph(c).iter().enumerate() {
+            for column in 0..font::GLYPH_WIDTH {
+                if bits & (1 << (font::GLYPH_WIDTH - 1 - column)) != 0 {
+                    renderer.fill_rect(
+                        Rect::new(
+                            pen_x + column as i32 * scale_signed,
+                            pen_y + row as i32 * scale_signed,
+                            scale,
+                            scale,
+                        ),
+                        color,
+                    )?;
+                }
+            }
+        }
+
+        pen_x += font::ADVANCE as i32 * scale_signed;
+    }
+
+    Ok(())
+}
+
+/// Width and height `text` takes up when drawn with `draw_bitmap_text` at `scale`
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let lines = text.split('\n');
+    let (width, count) = lines.fold((0, 0), |(width, count), line| {
+        (width.max(line.chars().count() as u32), count + 1)
+    });
+
+    (
+        (width * font::ADVANCE).saturating_sub(font::ADVANCE - font::GLYPH_WIDTH) * scale,
+        (count * font::LINE_HEIGHT - (font::LINE_HEIGHT - font::GLYPH_HEIGHT)) * scale,
+    )
+}
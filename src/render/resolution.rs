@@ -0,0 +1,98 @@
+/// How much of a frame's time the average can use before the scale goes back up,
+/// so it doesn't bounce between two scales either side of the target
+const HEADROOM: f64 = 0.8;
+/// How much the scale changes by at a time
+const STEP: f64 = 0.05;
+/// Frames to wait after changing the scale before changing it again, letting the average catch up
+const COOLDOWN: u32 = 15;
+/// Weight a new frame time has in the running average
+const SMOOTHING: f64 = 0.1;
+
+/// Picks a render scale that keeps frames within a target time, lowering it when frames run long and
+/// raising it again when there's time to spare
+#[derive(Debug, Clone)]
+pub struct DynamicResolution {
+    /// Seconds a frame should take at most
+    pub target: f64,
+    /// Lowest scale it drops to, however long frames take
+    pub min_scale: f64,
+    /// Highest scale it climbs back to
+    pub max_scale: f64,
+    scale: f64,
+    /// Running average of frame times in seconds
+    average: Option<f64>,
+    since_change: u32,
+}
+impl DynamicResolution {
+    /// Aim for frames taking `target` seconds at most, starting at full scale
+    pub fn new(target: f64) -> Self {
+        Self {
+            target,
+            min_scale: 0.25,
+            max_scale: 1.,
+            scale: 1.,
+            average: None,
+            since_change: 0,
+        }
+    }
+
+    /// Fraction of the full resolution to draw at now
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Account for a frame that took `frame_time` seconds to cast and draw, returning the scale to use next
+    pub fn update(&mut self, frame_time: f64) -> f64 {
+        let average = match self.average {
+            Some(average) => average + (frame_time - average) * SMOOTHING,
+            None => frame_time,
+        };
+        self.average = Some(average);
+
+        self.since_change += 1;
+        if self.since_change < COOLDOWN {
+            return self.scale;
+        }
+
+        let scale = if average > self.target {
+            self.scale - STEP
+        } else if average < self.target * HEADROOM {
+            self.scale + STEP
+        } else {
+            self.scale
+        };
+        // Kept to whole steps, so repeated steps don't drift and there are only so many sizes to render at
+        let scale = ((scale / STEP).round() * STEP).clamp(self.min_scale, self.max_scale);
+
+        if scale != self.scale {
+            self.scale = scale;
+            self.since_change = 0;
+        }
+        self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frames_lower_the_scale_down_to_the_minimum() {
+        let mut resolution = DynamicResolution::new(1. / 60.);
+        for _ in 0..1000 {
+            resolution.update(1. / 20.);
+        }
+        assert_eq!(resolution.scale(), resolution.min_scale);
+    }
+
+    #[test]
+    fn settles_where_frames_fit_the_target() {
+        // Frame time proportional to the scale, hitting the target at 0.5
+        let mut resolution = DynamicResolution::new(0.01);
+        let mut scale = resolution.scale();
+        for _ in 0..1000 {
+            scale = resolution.update(0.02 * scale);
+        }
+        assert!((0.4..=0.5).contains(&scale), "{}", scale);
+    }
+}
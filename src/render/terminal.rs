@@ -0,0 +1,126 @@
+//! Turning images into text for terminals, so a `Framebuffer` can be shown without a display
+
+use std::fmt::Write;
+
+use crate::{color::Color, image::Image};
+
+/// Characters from empty to solid, picked by how bright a pixel is
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+/// How character cells show an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalMode {
+    /// A character from `RAMP` per two pixels stacked vertically, by their brightness, as cells are about twice as
+    /// tall as they're wide. Works in any terminal
+    Ascii,
+    /// An upper half block per two pixels stacked vertically, its foreground the top pixel and background
+    /// the bottom one. Needs a terminal with 24 bit color
+    HalfBlock,
+}
+impl TerminalMode {
+    /// Width and height of the image a `columns` by `rows` grid of cells shows. Every mode puts two pixels
+    /// stacked vertically in a cell
+    pub fn image_size(self, columns: u16, rows: u16) -> (u32, u32) {
+        (columns as u32, rows as u32 * 2)
+    }
+}
+
+/// Write `image` to `out` as ANSI escaped text, from the top left of the terminal.
+/// Rows end with `\r\n` so they line up in raw mode as well
+pub fn encode(image: &Image, mode: TerminalMode, out: &mut String) {
+    out.push_str("\x1b[H");
+
+    match mode {
+        TerminalMode::Ascii => {
+            for y in (0..image.height).step_by(2) {
+                if y > 0 {
+                    out.push_str("\r\n");
+                }
+                for x in 0..image.width {
+                    // An odd height leaves the last row on its own
+                    let brightness = if y + 1 < image.height {
+                        (luma(pixel(image, x, y)) + luma(pixel(image, x, y + 1))) / 2.
+                    } else {
+                        luma(pixel(image, x, y))
+                    };
+                    let idx = (brightness * (RAMP.len() - 1) as f64).round() as usize;
+                    out.push(RAMP[idx] as char);
+                }
+            }
+        }
+        TerminalMode::HalfBlock => {
+            // Colors only change when they have to, most neighbouring cells share them
+            let mut current: Option<(Color, Color)> = None;
+            for y in (0..image.height).step_by(2) {
+                if y > 0 {
+                    out.push_str("\r\n");
+                }
+                for x in 0..image.width {
+                    let top = pixel(image, x, y);
+                    let bottom = pixel(image, x, y + 1);
+                    if current != Some((top, bottom)) {
+                        // Writing to a `String` can't fail
+                        let _ = write!(
+                            out,
+                            "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                            top.r, top.g, top.b, bottom.r, bottom.g, bottom.b
+                        );
+                        current = Some((top, bottom));
+                    }
+                    out.push('▀');
+                }
+            }
+        }
+    }
+
+    out.push_str("\x1b[0m");
+}
+
+/// Pixel at `(x, y)`, black outside the image
+fn pixel(image: &Image, x: u32, y: u32) -> Color {
+    image.pixel(x, y).unwrap_or(Color::BLACK)
+}
+
+/// Perceived brightness of `color` from 0 to 1
+fn luma(color: Color) -> f64 {
+    (0.299 * color.r as f64 + 0.587 * color.g as f64 + 0.114 * color.b as f64) / 255.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_ramps_from_black_to_white() {
+        // Rows 0 and 1 share a line, row 2 is on its own
+        let mut image = Image::new(2, 3, Color::BLACK);
+        image.pixels[4..8].copy_from_slice(&[255, 255, 255, 255]);
+        image.pixels[12..16].copy_from_slice(&[255, 255, 255, 255]);
+        image.pixels[20..24].copy_from_slice(&[255, 255, 255, 255]);
+
+        let mut out = String::new();
+        encode(&image, TerminalMode::Ascii, &mut out);
+        assert_eq!(out, "\x1b[H @\r\n @\x1b[0m");
+
+        image.pixels[12..16].copy_from_slice(&[0, 0, 0, 255]);
+        out.clear();
+        encode(&image, TerminalMode::Ascii, &mut out);
+        assert_eq!(out, "\x1b[H +\r\n @\x1b[0m");
+        assert_eq!(TerminalMode::Ascii.image_size(80, 24), (80, 48));
+    }
+
+    #[test]
+    fn half_blocks_stack_two_rows_per_line() {
+        let mut image = Image::new(2, 3, Color::RED);
+        image.pixels[8..].copy_from_slice(&[0, 0, 255, 255].repeat(4));
+
+        let mut out = String::new();
+        encode(&image, TerminalMode::HalfBlock, &mut out);
+        assert_eq!(
+            out,
+            "\x1b[H\x1b[38;2;255;0;0;48;2;0;0;255m▀▀\r\n\
+             \x1b[38;2;0;0;255;48;2;0;0;0m▀▀\x1b[0m"
+        );
+        assert_eq!(TerminalMode::HalfBlock.image_size(80, 24), (80, 48));
+    }
+}
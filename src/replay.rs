@@ -0,0 +1,226 @@
+//! Recording the input the game gets each tick, to play a session back exactly as it went
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use raycast::{math::vector::Vec2, render::Fov};
+
+use crate::{
+    key_state_handler::{Key, KeyStateHandler},
+    state::{Settings, State},
+};
+
+/// Everything that went into one `State::update`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tick {
+    /// Seconds the update advanced the game by
+    pub dt: f64,
+    /// Names of the keys held down, as SDL names them
+    pub keys: Vec<String>,
+    /// Horizontal mouse movements since the last tick, each passed to `State::mouse_motion`
+    #[serde(default)]
+    pub mouse: Vec<i32>,
+    /// Whether the player tried to open or close a door
+    #[serde(default)]
+    pub interact: bool,
+    /// How the game was set up from this tick on, when the player changed it from the console or menus
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup: Option<Setup>,
+    /// Where the player teleported to with the console's `tp`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teleport: Option<(f64, f64)>,
+}
+impl Tick {
+    /// Record the keys held in `state`, ready for it to be advanced by `dt`, and its setup if it's changed from
+    /// `last`, the setup as of the tick before, which is brought up to date
+    #[cfg(any(feature = "sdl", test))]
+    pub fn finish(&mut self, state: &State, last: &mut Setup, dt: f64) {
+        let setup = Setup::of(state);
+        if setup != *last {
+            self.setup = Some(setup);
+            *last = setup;
+        }
+        self.dt = dt;
+        self.keys = state
+            .keys
+            .pressed()
+            .map(|key| key.name().to_owned())
+            .collect();
+        self.keys.sort();
+    }
+
+    /// Put this tick's input into `state` and advance it, just as it was when recorded
+    pub fn apply(&self, state: &mut State) {
+        if let Some(setup) = self.setup {
+            setup.apply(state);
+        }
+        if let Some((x, y)) = self.teleport {
+            state.teleport(Vec2::new(x, y));
+        }
+        state.keys = KeyStateHandler::new();
+        for key in self.keys.iter().filter_map(|name| Key::from_name(name)) {
+            state.keys.press(key);
+        }
+        for &xrel in self.mouse.iter() {
+            state.mouse_motion(xrel);
+        }
+        if self.interact {
+            state.interact();
+        }
+        state.update(self.dt);
+    }
+}
+
+/// What the player can change about the game besides their input, from the console or menus
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Setup {
+    pub settings: Settings,
+    pub fov: Fov,
+    pub noclip: bool,
+}
+impl Setup {
+    /// How `state` is set up now
+    #[cfg(any(feature = "sdl", test))]
+    pub fn of(state: &State) -> Self {
+        Self {
+            settings: state.settings,
+            fov: state.fov,
+            noclip: state.noclip,
+        }
+    }
+
+    /// Set `state` up this way
+    pub fn apply(&self, state: &mut State) {
+        state.settings = self.settings;
+        state.fov = self.fov;
+        state.noclip = self.noclip;
+    }
+}
+
+/// A map and setup to start from and the input for each tick after. Changes to the setup and teleports are
+/// recorded with the tick they came before, the map can't change part way through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub map: String,
+    #[serde(flatten)]
+    pub setup: Setup,
+    pub ticks: Vec<Tick>,
+}
+impl Replay {
+    /// Start recording from the beginning of the map `state` is on, restarting it
+    #[cfg(any(feature = "sdl", test))]
+    pub fn record(state: &mut State) -> Result<Self, String> {
+        let map = state.map_path.clone();
+        state.load_map(&map).map_err(|e| e.to_string())?;
+        Ok(Self {
+            map,
+            setup: Setup::of(state),
+            ticks: vec![],
+        })
+    }
+
+    /// Put `state` back where the recording started, ready for the first tick
+    pub fn restart(&self, state: &mut State) -> Result<(), String> {
+        state.load_map(&self.map).map_err(|e| e.to_string())?;
+        self.setup.apply(state);
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+
+    #[cfg(any(feature = "sdl", test))]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Seconds it lasts
+    #[cfg(any(feature = "sdl", test))]
+    pub fn duration(&self) -> f64 {
+        self.ticks.iter().map(|tick| tick.dt).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raycast::render::Viewport;
+
+    use super::*;
+
+    /// A replay of walking and turning around the default map, and the state it finished in
+    fn recorded() -> (Replay, State) {
+        let mut state = State::new(Viewport::new(320, 180));
+        let mut replay = Replay::record(&mut state).unwrap();
+        let mut setup = replay.setup;
+        for idx in 0..120 {
+            let mut tick = Tick::default();
+            state.keys = KeyStateHandler::new();
+            state.keys.press(if idx < 60 { Key::W } else { Key::D });
+            if idx % 10 == 0 {
+                tick.mouse.push(idx - 50);
+                state.mouse_motion(idx - 50);
+            }
+            if idx == 30 {
+                tick.interact = true;
+                state.interact();
+            }
+            // The player changing the game part way through, from the console or menus
+            if idx == 45 {
+                let point = state.camera.position + Vec2::new(-20., 10.);
+                tick.teleport = Some((point.x, point.y));
+                state.teleport(point);
+            }
+            if idx == 90 {
+                state.settings.move_speed *= 2.;
+                state.noclip = true;
+            }
+
+            let dt = 1. / 60.;
+            tick.finish(&state, &mut setup, dt);
+            state.update(dt);
+            replay.ticks.push(tick);
+        }
+        (replay, state)
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let (replay, _) = recorded();
+        let path = std::env::temp_dir().join(format!("raycast-replay-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        replay.save(path).unwrap();
+        let loaded = Replay::load(path);
+        fs::remove_file(path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&loaded).unwrap(),
+            serde_json::to_string(&replay).unwrap()
+        );
+        assert_eq!(loaded.ticks[0].keys, ["W"]);
+        assert_eq!(loaded.ticks[45].teleport, replay.ticks[45].teleport);
+        assert!(loaded.ticks[90].setup.unwrap().noclip);
+        assert!(loaded.ticks[91].setup.is_none());
+        assert_eq!(loaded.duration(), replay.duration());
+    }
+
+    #[test]
+    fn playing_back_ends_where_recording_did() {
+        let (replay, recorded) = recorded();
+
+        let mut state = State::new(Viewport::new(320, 180));
+        replay.restart(&mut state).unwrap();
+        let start = state.camera.position;
+        for tick in replay.ticks.iter() {
+            tick.apply(&mut state);
+        }
+
+        assert_ne!(state.camera.position, start);
+        assert_eq!(state.camera.position, recorded.camera.position);
+        assert_eq!(state.camera.angle, recorded.camera.angle);
+    }
+}
@@ -1,323 +1,356 @@
 use std::collections::HashMap;
 
-use num_traits::{AsPrimitive, Float};
-use sdl2::{
-    keyboard::Keycode,
-    pixels::Color,
-    rect::{Point, Rect},
-    render::Canvas,
-    video::Window,
+use raycast::{
+    caster,
+    map::{Map, MapError},
+    math::{segment::Segment, vector::Vec2, wall::Surface},
+    render::{self, Camera, Fov, Frame, Minimap, Renderer, Viewport},
 };
 
-use crate::{
-    ext::ColorExt, key_state_handler::KeyStateHandler, map::Map, math::vector::Vec2D,
-    WINDOW_HEIGHT, WINDOW_WIDTH,
-};
-
-pub fn remap<
-    T: 'static + Float + Copy,
-    ON: AsPrimitive<T> + Copy,
-    OX: AsPrimitive<T> + Copy,
-    NN: AsPrimitive<T> + Copy,
-    NX: AsPrimitive<T> + Copy,
->(
-    value: T,
-    old_min: ON,
-    old_max: OX,
-    new_min: NN,
-    new_max: NX,
-) -> T {
-    new_min.as_()
-        + (new_max.as_() - new_min.as_())
-            * ((value - old_min.as_()) / (old_max.as_() - old_min.as_()))
-}
+use serde::{Deserialize, Serialize};
 
-pub fn remap_minz<
-    T: 'static + Float + Copy + AsPrimitive<T>,
-    OX: AsPrimitive<T> + Copy,
-    NX: AsPrimitive<T> + Copy,
->(
-    value: T,
-    old_max: OX,
-    new_max: NX,
-) -> T {
-    remap(value, T::zero(), old_max, T::zero(), new_max)
-}
+use crate::key_state_handler::{Key, KeyStateHandler};
 
+const DEFAULT_MAP: &str = "./assets/maps/many_walls.json";
 const MOUSE_SENSITIVITY: f64 = 0.01;
 const MOVE_SPEED: f64 = 2.5;
 const PLAYER_WALL_PADDING: f64 = 10.;
-const WALL_ACTUAL_HEIGHT: f64 = 48.;
+/// How close the player has to be to a door to open or close it
+const DOOR_REACH: f64 = 64.;
+/// How far in front of a portal's exit the player comes out
+const PORTAL_EXIT_OFFSET: f64 = 1.;
+/// About 60 degrees across a 16:9 screen, seeing more to the sides on wider ones rather than stretching
+const DEFAULT_FOV: Fov = Fov::Vertical(36.);
+/// Narrowest and widest the field of view can be set to, in degrees
+#[cfg(feature = "sdl")]
+pub const FOV_RANGE: (f64, f64) = (10., 150.);
+/// Lowest and highest the mouse sensitivity can be set to, in radians per pixel
+#[cfg(feature = "sdl")]
+pub const MOUSE_SENSITIVITY_RANGE: (f64, f64) = (0.001, 0.05);
+/// Slowest and fastest the player can be set to move, in map units an update
+#[cfg(feature = "sdl")]
+pub const MOVE_SPEED_RANGE: (f64, f64) = (0.5, 10.);
+/// Magnification while zooming
+const ZOOM: f64 = 2.5;
+/// Map units a second the arrow keys pan the minimap by, at its normal zoom
+const MINIMAP_PAN_SPEED: f64 = 300.;
+
+/// What can be tweaked while playing, from the console
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Radians the camera turns per pixel the mouse moves
+    pub mouse_sensitivity: f64,
+    /// Map units the player moves each update
+    pub move_speed: f64,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: MOUSE_SENSITIVITY,
+            move_speed: MOVE_SPEED,
+        }
+    }
+}
 
 pub struct State {
-    pub(crate) position: Vec2D,
-    pub(crate) angle: f64,
-    pub(crate) fov: f64,
-    pub(crate) wall_colors: Vec<Color>,
+    pub(crate) camera: Camera,
     pub(crate) map: Map,
+    /// Where `map` was loaded from
+    pub(crate) map_path: String,
     pub(crate) keys: KeyStateHandler,
-    pub(crate) columns: Vec<(usize, u32)>,
-    pub(crate) resolution: usize,
-    pub(crate) projection_factor: f64,
-    pub(crate) radian_per_column: f64,
-    pub(crate) column_width: u32,
+    pub(crate) frame: Frame,
+    /// Field of view when not zoomed in, the camera's following it and the viewport's aspect ratio each update
+    pub(crate) fov: Fov,
+    pub(crate) minimap: Minimap,
+    pub(crate) settings: Settings,
+    /// Move through walls and past portals without being stopped or teleported
+    pub(crate) noclip: bool,
+    /// Seconds the current map has been played for
+    pub(crate) time: f64,
 }
 impl State {
-    pub fn new() -> Self {
-        let wall_colors = vec![
-            Color::RGB(128, 255, 0),
-            Color::RGB(0, 128, 255),
-            Color::RGB(255, 0, 128),
-            Color::RGB(0, 255, 0),
-            Color::RGB(0, 0, 255),
-            Color::WHITE,
-        ];
-
+    /// Load the map and start on it, the view cast across `viewport` until it's changed
+    pub fn new(viewport: Viewport) -> Self {
         // let map = Map::default();
-        let map = Map::load("./assets/maps/many_walls.json").unwrap();
-        let (w, h) = map.dims;
-        // let movement_vector = Line::new(origin, origin + geo::Point::new(delta_x, delta_y));
-        let position = Vec2D::new(w as f64 / 2., 50. + h as f64 / 2.);
-        let fov = 60.;
-        let projection_plane_distance =
-            ((WINDOW_WIDTH / 2) as f64 / (fov.to_radians() / 2.).tan()) as f64;
+        Self::with_map(viewport, DEFAULT_MAP).unwrap()
+    }
 
-        let resolution = WINDOW_WIDTH as usize;
+    /// Load the map at `path` and start on it, the view cast across `viewport` until it's changed
+    pub fn with_map(viewport: Viewport, path: &str) -> Result<Self, MapError> {
+        let map = Self::read_map(path)?;
 
-        Self {
-            position,
-            angle: std::f64::consts::PI,
-            fov,
-            wall_colors,
+        let camera = Camera {
+            position: map.spawn.position,
+            angle: map.spawn.angle,
+            fov: DEFAULT_FOV.horizontal(viewport.aspect()),
+        };
+
+        Ok(Self {
+            camera,
             map,
+            map_path: path.to_owned(),
             keys: KeyStateHandler::new(),
-            columns: Vec::with_capacity(resolution),
-            resolution,
-            projection_factor: projection_plane_distance * WALL_ACTUAL_HEIGHT,
-            radian_per_column: fov.to_radians() / resolution as f64,
-            column_width: WINDOW_WIDTH / resolution as u32,
-        }
+            frame: Frame::new(camera, viewport),
+            fov: DEFAULT_FOV,
+            minimap: Minimap::default(),
+            settings: Settings::default(),
+            noclip: false,
+            time: 0.,
+        })
     }
 
-    fn get_color(&self, index: usize) -> Color {
-        self.wall_colors.get(index).copied().unwrap_or(Color::WHITE)
+    /// Swap to the map at `path`, starting again at its spawn
+    pub fn load_map(&mut self, path: &str) -> Result<(), MapError> {
+        self.map = Self::read_map(path)?;
+        self.map_path = path.to_owned();
+        self.time = 0.;
+        self.camera.position = self.map.spawn.position;
+        self.camera.angle = self.map.spawn.angle;
+        self.minimap.forget();
+        self.minimap.pan = Vec2::ZERO;
+        Ok(())
     }
 
-    pub fn mouse_motion(&mut self, dx: i32) {
-        self.angle += MOUSE_SENSITIVITY * dx as f64;
+    /// Load the map at `path`, saying what was loaded on standard error, which leaves standard output to the
+    /// benchmark's results
+    fn read_map(path: &str) -> Result<Map, MapError> {
+        let map = Map::load(path)?;
+        eprintln!(
+            "Successfully loaded {} walls from \"{}\"",
+            map.walls.len(),
+            path
+        );
+        for warning in map.warnings.iter() {
+            eprintln!("Warning: {}", warning);
+        }
+        if let Some(name) = &map.meta.name {
+            eprintln!(
+                "{} by {}",
+                name,
+                map.meta.author.as_deref().unwrap_or("unknown")
+            );
+        }
+        if let Some(description) = &map.meta.description {
+            eprintln!("{}", description);
+        }
+        Ok(map)
     }
 
-    fn calculate_collisions(&mut self) {
-        let mut current_angle = self.angle - (self.fov.to_radians() / 2.);
-        let end_angle = current_angle + (self.radian_per_column * self.resolution as f64);
-
-        self.columns.clear();
-
-        for _ in 0..self.resolution {
-            let mut ray = Vec2D::from_angle(current_angle);
-            ray.translate(&self.position);
-
-            let mut max_height = f64::NEG_INFINITY;
-            let mut wall_color_index = 0;
-
-            for wall in self.map.walls.iter() {
-                if let Some(intersection_vector) = ray.intersects(wall) {
-                    let raw_distance = ray.dist(&intersection_vector);
-                    let delta = current_angle - self.angle;
-                    let corrected_distance = raw_distance * (delta.cos() as f64);
-                    let projected_height = self.projection_factor / corrected_distance;
-
-                    if projected_height > max_height {
-                        max_height = projected_height;
-                        wall_color_index = wall.color_index;
-                    }
-                }
-            }
-            if max_height.is_infinite() {
-                self.columns.push((0, 0));
-            } else {
-                self.columns
-                    .push((wall_color_index, max_height.round() as u32));
-            }
+    pub fn mouse_motion(&mut self, dx: i32) {
+        self.turn(self.settings.mouse_sensitivity * dx as f64);
+    }
 
-            current_angle += self.radian_per_column;
-        }
+    /// Turn the camera clockwise by `angle` radians
+    pub fn turn(&mut self, angle: f64) {
+        self.camera.angle += angle;
     }
 
     fn update_camera(&mut self) {
-        let mut delta = Vec2D::Origin;
+        let mut delta = Vec2::ZERO;
 
-        let par = Vec2D::from_angle(self.angle as f64);
-        let perp = Vec2D::from_angle(self.angle + (90f64).to_radians());
+        let par = Vec2::from_angle(self.camera.angle);
+        let perp = par.perp();
 
-        if self.keys.is_pressed(Keycode::W) {
+        if self.keys.is_pressed(Key::W) {
             delta += par;
         }
-        if self.keys.is_pressed(Keycode::S) {
+        if self.keys.is_pressed(Key::S) {
             delta += -par;
         }
-        if self.keys.is_pressed(Keycode::A) {
+        if self.keys.is_pressed(Key::A) {
             delta += -perp;
         }
-        if self.keys.is_pressed(Keycode::D) {
+        if self.keys.is_pressed(Key::D) {
             delta += perp;
         }
 
         // Normalize delta so that the player doesn't move faster moving in a diagonal direction
-        if !delta.is_origin() {
-            delta = delta.normalize() * MOVE_SPEED;
+        if !delta.is_zero() {
+            delta = delta.normalize() * self.settings.move_speed;
+        }
+
+        let target = self.camera.position + delta;
+        if self.noclip {
+            self.camera.position = target.clamp(self.map.dims, 0.);
+            return;
+        }
+        if self.step_through_portal(target) {
+            return;
         }
 
-        self.position.add_x_y_raw(delta.x_y());
-        self.position.clamp(self.map.dims, PLAYER_WALL_PADDING);
+        // Slide along walls by trying each axis on its own when the full move is blocked
+        for step in [delta, Vec2::new(delta.x, 0.), Vec2::new(0., delta.y)] {
+            let target = self.camera.position + step;
+            if !self.blocked(self.camera.position, target) {
+                self.camera.position = target;
+                break;
+            }
+        }
+
+        self.camera.position = self
+            .camera
+            .position
+            .clamp(self.map.dims, PLAYER_WALL_PADDING);
     }
 
-    pub fn update(&mut self) {
-        self.update_camera();
-        self.calculate_collisions();
+    /// Teleport the player if moving to `target` crosses a portal, returning whether the move was taken care of.
+    /// A portal whose way out is blocked stops the player like a wall would
+    fn step_through_portal(&mut self, target: Vec2) -> bool {
+        let walls = &self.map.walls;
+        let path = Segment::new(self.camera.position, target);
+        let crossed = walls.iter().find_map(|wall| match wall.surface {
+            Surface::Portal { target: exit } => path
+                .intersect(&wall.segment())
+                .map(|point| (wall, exit, point)),
+            _ => None,
+        });
+
+        let (portal, exit_index, point) = match crossed {
+            Some(crossed) => crossed,
+            None => return false,
+        };
+
+        let heading = path.direction().angle();
+        let (exit_point, exit_heading) =
+            caster::through_portal(portal, &walls[exit_index], point, heading);
+
+        // Carry on for the rest of the move, and far enough that the player isn't left on the exit itself
+        let forward = Vec2::from_angle(exit_heading);
+        let remaining = target.distance(point).max(PORTAL_EXIT_OFFSET);
+        let destination =
+            (exit_point + forward * remaining).clamp(self.map.dims, PLAYER_WALL_PADDING);
+        // Starting just off the exit, so the exit itself isn't in the way. The player can't already be too close to
+        // the walls round the exit, so unlike walking they can't come out pressed up against one either
+        let cramped = walls.iter().enumerate().any(|(idx, wall)| {
+            idx != exit_index
+                && !matches!(wall.surface, Surface::Portal { .. })
+                && wall.distance_to(destination) < PLAYER_WALL_PADDING
+        });
+        if cramped || self.blocked(exit_point + forward * PORTAL_EXIT_OFFSET, destination) {
+            return true;
+        }
+
+        self.camera.position = destination;
+        self.camera.angle += exit_heading - heading;
+        true
     }
 
-    pub fn draw_minimap(
-        &self,
-        canvas: &mut Canvas<Window>,
-        dims: (f64, f64),
-    ) -> Result<(), String> {
-        let minimap_offset = (dims.0.max(dims.1) / 4.);
-        let minimap_base = Vec2D::new(minimap_offset, minimap_offset);
-        // Background
-        canvas.set_draw_color(Color::BLACK);
-        canvas.fill_rect(Rect::new(
-            minimap_offset as i32,
-            minimap_offset as i32,
-            dims.0 as u32,
-            dims.1 as u32,
-        ))?;
-
-        canvas.set_draw_color(Color::WHITE);
-
-        // Player position
-        let position_mapped = self.position.remap(self.map.dims, dims) + minimap_base;
-        canvas.fill_rect(Rect::from_center(position_mapped, 8, 8))?;
-
-        // Player lines
-        let ray_scale = dims.0.max(dims.1) / 2.;
-        let half_fov = self.fov.to_radians() / 2.;
-
-        let forward_end = position_mapped + (Vec2D::from_angle(self.angle) * ray_scale);
-        let left_end = position_mapped + (Vec2D::from_angle(self.angle - half_fov) * ray_scale);
-        let right_end = position_mapped + (Vec2D::from_angle(self.angle + half_fov) * ray_scale);
-
-        canvas.draw_lines(&[
-            position_mapped.into(),
-            forward_end.into(),
-            position_mapped.into(),
-            left_end.into(),
-            position_mapped.into(),
-            right_end.into(),
-        ] as &[Point])?;
-
-        // TODO: FOV lines
-
-        // Walls
-        for wall in self.map.walls.iter() {
-            canvas.set_draw_color(self.get_color(wall.color_index));
-
-            let start = wall.a.remap(self.map.dims, dims) + minimap_base;
-            let end = wall.b.remap(self.map.dims, dims) + minimap_base;
-
-            canvas.draw_line(start, end)?;
+    /// Whether moving from `from` to `target` would take the player through or too close to a wall.
+    /// Moving away from a wall the player is already too close to is allowed, so they can't get stuck.
+    /// Portals never block, the player steps through them instead
+    fn blocked(&self, from: Vec2, target: Vec2) -> bool {
+        let path = Segment::new(from, target);
+        self.map.walls.iter().any(|wall| {
+            if let Surface::Portal { .. } = wall.surface {
+                return false;
+            }
+
+            let distance = wall.distance_to(target);
+            let too_close = distance < PLAYER_WALL_PADDING && distance < wall.distance_to(from);
+
+            too_close || path.intersect(&wall.segment()).is_some()
+        })
+    }
+
+    fn update_doors(&mut self, dt: f64) {
+        let position = self.camera.position;
+        for door in self.map.doors.iter_mut() {
+            // Doors swinging or sliding either way mustn't push into the player
+            door.update_clear_of(dt, position, PLAYER_WALL_PADDING);
+            door.apply(&mut self.map.walls);
         }
+    }
 
-        // let mut current_angle = self.angle + (self.fov.to_radians() / 2.);
-
-        // for _ in 0..self.resolution {
-        //     let mut ray = self.position;
-        //     ray.set_angle(current_angle);
-        //     ray += self.position;
-
-        //     let mut max_height = f64::NEG_INFINITY;
-        //     let mut collisions: Vec<(bool, Vec2D)> = vec![];
-        //     let mut collision = Vec2D::Origin;
-
-        //     for wall in self.map.walls.iter() {
-        //         if let Some(intersection_vector) = ray.intersects(wall) {
-        //             let raw_distance = ray.dist(&intersection_vector);
-        //             let delta = current_angle - self.angle;
-        //             let corrected_distance = raw_distance * (delta.cos() as f64);
-        //             let projected_height = self.projection_factor / corrected_distance;
-
-        //             if projected_height > max_height {
-        //                 max_height = projected_height;
-        //                 collisions = collisions.into_iter().fold(vec![], |mut acc, cur| {
-        //                     acc.push((false, cur.1));
-        //                     acc
-        //                 });
-        //                 collisions.push((true, collision));
-        //                 collision = intersection_vector;
-        //             } else {
-        //                 collisions.push((false, intersection_vector));
-        //             }
-        //         }
-        //     }
-        //     if !max_height.is_infinite() {
-        //         canvas.set_draw_color(Color::RED);
-        //         canvas.draw_rects(
-        //             collisions
-        //                 .into_iter()
-        //                 .map(|(_, v)| {
-        //                     Rect::from_center(
-        //                         Point::new(
-        //                             remap(v.x(), 0., self.map.dims.0, 0., dims.0).floor() as i32,
-        //                             remap(v.y(), 0., self.map.dims.1, 0., dims.1).floor() as i32,
-        //                         ) + minimap_base,
-        //                         2,
-        //                         2,
-        //                     )
-        //                 })
-        //                 .collect::<Vec<Rect>>()
-        //                 .as_slice(),
-        //         );
-        //     }
-
-        //     current_angle -= self.radian_per_column;
-        // }
+    /// Put the player at `point`, kept inside the map as walking would, returning where they ended up
+    pub fn teleport(&mut self, point: Vec2) -> Vec2 {
+        self.camera.position = point.clamp(self.map.dims, PLAYER_WALL_PADDING);
+        self.camera.position
+    }
 
-        Ok(())
+    /// Open or close the nearest door within reach
+    pub fn interact(&mut self) {
+        let position = self.camera.position;
+        let nearest = self
+            .map
+            .doors
+            .iter_mut()
+            .map(|door| (door.closed_distance_to(position), door))
+            .filter(|(distance, _)| *distance <= DOOR_REACH)
+            .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some((_, door)) = nearest {
+            door.interact();
+        }
+    }
+
+    /// Widen the field of view by `degrees`, or narrow it when negative
+    #[cfg(feature = "sdl")]
+    pub fn adjust_fov(&mut self, degrees: f64) {
+        self.set_fov(|fov| fov + degrees);
     }
 
-    fn render_frame(&self, canvas: &mut Canvas<Window>) -> Result<(), String> {
-        let column_width_signed = self.column_width as i32;
+    /// Set the field of view to what `f` makes of it, in degrees, keeping it within range
+    #[cfg(feature = "sdl")]
+    pub fn set_fov(&mut self, f: impl FnOnce(f64) -> f64) {
+        self.fov = self.fov.map(|fov| f(fov).clamp(FOV_RANGE.0, FOV_RANGE.1));
+    }
 
-        // TODO: Draw background
+    /// Hold the field of view fixed across the other axis of the viewport, seeing the same as now
+    #[cfg(feature = "sdl")]
+    pub fn switch_fov_axis(&mut self) {
+        self.fov = self.fov.switch_axis(self.frame.viewport.aspect());
+        self.set_fov(|fov| fov);
+    }
 
-        let mut current_index = usize::MAX;
-        let mut current_color = Color::BLACK;
+    /// Advance the game by `dt` seconds, ready for `cast`
+    pub fn update(&mut self, dt: f64) {
+        self.time += dt;
+        self.update_doors(dt);
+        self.update_camera();
 
-        for (idx, (color_index, height)) in self.columns.iter().copied().enumerate() {
-            if color_index != current_index {
-                current_color = self.get_color(color_index);
-                current_index = color_index;
-            }
+        let fov = if self.keys.is_pressed(Key::Z) {
+            self.fov.zoomed(ZOOM)
+        } else {
+            self.fov
+        };
+        // Kept below 180, where the projection plane would be infinitely wide
+        self.camera.fov = fov.horizontal(self.frame.viewport.aspect()).min(170.);
+        self.pan_minimap(dt);
+    }
 
-            let dim_amt = remap(height as f64, 0, WINDOW_HEIGHT, 255, 0).floor() as u8;
-
-            canvas.set_draw_color(current_color.dim(dim_amt));
-            canvas.fill_rect(Rect::from_center(
-                Point::new(
-                    idx as i32 * column_width_signed + (column_width_signed / 2),
-                    WINDOW_HEIGHT as i32 / 2,
-                ),
-                self.column_width,
-                height,
-            ))?;
+    /// Cast the view from where the camera is now
+    pub fn cast(&mut self) {
+        self.frame.cast(&self.map, self.camera, self.time);
+        self.minimap.reveal(&self.frame, &self.map);
+    }
+
+    fn pan_minimap(&mut self, dt: f64) {
+        let mut direction = Vec2::ZERO;
+        for (key, step) in [
+            (Key::Left, Vec2::new(-1., 0.)),
+            (Key::Right, Vec2::new(1., 0.)),
+            (Key::Up, Vec2::new(0., -1.)),
+            (Key::Down, Vec2::new(0., 1.)),
+        ] {
+            if self.keys.is_pressed(key) {
+                direction += step;
+            }
         }
+        self.minimap.pan += direction * (MINIMAP_PAN_SPEED * dt / self.minimap.zoom);
+    }
 
-        Ok(())
+    /// Draw the view, at whatever size `self.frame`'s viewport is
+    pub fn draw<R: Renderer>(
+        &self,
+        renderer: &mut R,
+        sky: Option<&R::Texture<'_>>,
+    ) -> Result<(), String> {
+        render::draw_frame(renderer, &self.frame, &self.map, sky)
     }
 
-    pub fn draw(&self, canvas: &mut Canvas<Window>) -> Result<(), String> {
-        self.render_frame(canvas)?;
-        self.draw_minimap(canvas, (WINDOW_WIDTH as f64 / 5., WINDOW_WIDTH as f64 / 5.))?;
-        Ok(())
+    /// Draw what goes over the view, sized to fit `renderer`
+    #[cfg(feature = "sdl")]
+    pub fn draw_overlay<R: Renderer>(&self, renderer: &mut R) -> Result<(), String> {
+        self.minimap.draw(renderer, &self.frame, &self.map)
     }
 }
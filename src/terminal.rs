@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    key_state_handler::{Key, KeyStateHandler},
+    state::State,
+};
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, terminal,
+};
+use raycast::render::{
+    self,
+    terminal::{encode, TerminalMode},
+    Framebuffer, Renderer, Viewport,
+};
+
+const FPS: f64 = 30.;
+/// Radians a second the arrow keys turn the camera
+const TURN_SPEED: f64 = 2.5;
+/// Without key release events, a key counts as held for this long after it or one of its repeats came in
+const KEY_HOLD: Duration = Duration::from_millis(200);
+
+/// Puts the terminal back how it was when dropped, even when the game errors or panics
+struct RawTerminal {
+    key_releases: bool,
+}
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        // Terminals supporting it can report key releases, otherwise held keys have to be guessed from repeats
+        let key_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if key_releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Self { key_releases })
+    }
+}
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        if self.key_releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The key `code` is, if the game reads it
+fn game_key(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::Char(c) => match c.to_ascii_lowercase() {
+            'w' => Key::W,
+            'a' => Key::A,
+            's' => Key::S,
+            'd' => Key::D,
+            'e' => Key::E,
+            _ => return None,
+        },
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        _ => return None,
+    })
+}
+
+/// Play in the terminal, the view fitted to its size. Escape, `q` or Ctrl+C quit
+pub fn run(mode: TerminalMode) -> Result<(), String> {
+    let (columns, rows) = terminal::size().map_err(|e| e.to_string())?;
+    let (width, height) = mode.image_size(columns, rows);
+    let mut state = State::new(Viewport::new(width, height));
+    let raw = RawTerminal::enter().map_err(|e| e.to_string())?;
+
+    let mut held: HashMap<Key, Instant> = HashMap::new();
+    let mut framebuffer = Framebuffer::new(width, height);
+    let mut out = String::new();
+    let frame_time = Duration::from_secs_f64(1. / FPS);
+    let mut last_frame = Instant::now();
+
+    loop {
+        let start = Instant::now();
+        let dt = start.duration_since(last_frame).as_secs_f64();
+        last_frame = start;
+
+        // Wait out the rest of the frame for input
+        while let Some(timeout) = frame_time.checked_sub(start.elapsed()) {
+            if !event::poll(timeout).map_err(|e| e.to_string())? {
+                break;
+            }
+
+            let key = match event::read().map_err(|e| e.to_string())? {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+            let KeyEvent {
+                code,
+                modifiers,
+                kind,
+                ..
+            } = key;
+
+            let quit = code == KeyCode::Esc
+                || code == KeyCode::Char('q')
+                || (code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL));
+            if quit {
+                return Ok(());
+            }
+
+            let key = match game_key(code) {
+                Some(key) => key,
+                None => continue,
+            };
+            match kind {
+                KeyEventKind::Release => {
+                    held.remove(&key);
+                }
+                KeyEventKind::Press if key == Key::E => state.interact(),
+                _ => {
+                    held.insert(key, Instant::now());
+                }
+            }
+        }
+
+        if !raw.key_releases {
+            held.retain(|_, pressed| pressed.elapsed() < KEY_HOLD);
+        }
+        state.keys = KeyStateHandler::new();
+        for key in held.keys() {
+            state.keys.press(*key);
+        }
+        if state.keys.is_pressed(Key::Left) {
+            state.turn(-TURN_SPEED * dt);
+        }
+        if state.keys.is_pressed(Key::Right) {
+            state.turn(TURN_SPEED * dt);
+        }
+
+        // Follow the terminal's size, which can change at any time
+        let (columns, rows) = terminal::size().map_err(|e| e.to_string())?;
+        let (width, height) = mode.image_size(columns, rows);
+        if framebuffer.size() != (width, height) {
+            state.frame.viewport = Viewport::new(width, height);
+            framebuffer = Framebuffer::new(width, height);
+        }
+
+        state.update(dt);
+        state.cast();
+
+        framebuffer.clear(raycast::Color::BLACK)?;
+        render::draw_frame(
+            &mut framebuffer,
+            &state.frame,
+            &state.map,
+            state.map.sky.as_ref(),
+        )?;
+
+        out.clear();
+        encode(framebuffer.image(), mode, &mut out);
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(out.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| e.to_string())?;
+    }
+}
@@ -0,0 +1,98 @@
+//! Writing frames out as uncompressed video, for tools like ffmpeg to encode later
+
+use std::io::{self, Write};
+
+use crate::image::Image;
+
+/// Writes same sized images to a YUV4MPEG2 stream, full resolution 4:4:4 so no color is lost to subsampling
+#[derive(Debug)]
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: u32,
+    height: u32,
+    /// Reused between frames to convert into
+    planes: Vec<u8>,
+}
+impl<W: Write> Y4mWriter<W> {
+    /// Write the stream header for `width` by `height` frames played at `fps`
+    pub fn new(mut writer: W, width: u32, height: u32, fps: u32) -> io::Result<Self> {
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+            width, height, fps
+        )?;
+        Ok(Self {
+            writer,
+            width,
+            height,
+            planes: vec![],
+        })
+    }
+
+    /// Width and height of every frame
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Add a frame, which has to be the size given to `new`. Alpha is ignored
+    pub fn write_frame(&mut self, image: &Image) -> io::Result<()> {
+        if (image.width, image.height) != (self.width, self.height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Can't write a {}x{} frame to a {}x{} video",
+                    image.width, image.height, self.width, self.height
+                ),
+            ));
+        }
+
+        // Y, then Cb, then Cr, each a whole plane
+        let area = image.width as usize * image.height as usize;
+        self.planes.resize(area * 3, 0);
+        let (y_plane, chroma) = self.planes.split_at_mut(area);
+        let (cb_plane, cr_plane) = chroma.split_at_mut(area);
+        for (idx, pixel) in image.pixels.chunks_exact(4).enumerate() {
+            let (y, cb, cr) = ycbcr(pixel[0], pixel[1], pixel[2]);
+            y_plane[idx] = y;
+            cb_plane[idx] = cb;
+            cr_plane[idx] = cr;
+        }
+
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)
+    }
+
+    /// The writer the video went to, once done adding frames
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// BT.601 limited range, which is what players assume a Y4M without a color range tag is in
+fn ycbcr(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let y = 16. + 0.257 * r + 0.504 * g + 0.098 * b;
+    let cb = 128. - 0.148 * r - 0.291 * g + 0.439 * b;
+    let cr = 128. + 0.439 * r - 0.368 * g - 0.071 * b;
+    (y.round() as u8, cb.round() as u8, cr.round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn writes_a_header_then_planar_frames() {
+        let mut image = Image::new(2, 1, Color::BLACK);
+        image.pixels[4..].copy_from_slice(&[255, 255, 255, 255]);
+
+        let mut video = Y4mWriter::new(vec![], 2, 1, 30).unwrap();
+        video.write_frame(&image).unwrap();
+        assert!(video.write_frame(&Image::new(1, 1, Color::BLACK)).is_err());
+
+        let mut expected = b"YUV4MPEG2 W2 H1 F30:1 Ip A1:1 C444\nFRAME\n".to_vec();
+        expected.extend([16, 235, 128, 128, 128, 128]);
+        assert_eq!(video.into_inner(), expected);
+    }
+}